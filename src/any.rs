@@ -0,0 +1,126 @@
+use alloc::rc::Rc;
+use core::{
+    any::Any,
+    cell::{Ref, RefCell, RefMut},
+};
+
+use crate::{Imp, ImpRef, ImpRefMut};
+
+/*
+    The type-erased values an Imp can be downcast from.
+    Public so it can bound the methods below, but not nameable outside the crate.
+*/
+pub trait AnyValue {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl AnyValue for dyn Any {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl AnyValue for dyn Any + Send {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/*
+    Allows getting the concrete type back out of a pointer to a type-erased value.
+*/
+impl<A: ?Sized + AnyValue> Imp<A> {
+    /// Returns true if the inner value is of type `T`.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed, as the type is checked under a shared borrow.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::any::Any;
+    /// use interior_mutability_pointer::{imp_dyn, Imp};
+    /// let p: Imp<dyn Any> = unsafe { imp_dyn!(5) };
+    /// assert!(Imp::is::<i32>(&p));
+    /// assert!(!Imp::is::<String>(&p));
+    /// ```
+    #[track_caller]
+    pub fn is<T: Any>(this: &Self) -> bool {
+        match this.v.try_borrow() {
+            Ok(r) => r.as_any().is::<T>(),
+            Err(e) => panic!("already mutably borrowed: {:?}", e),
+        }
+    }
+
+    /// Turns the pointer into a pointer to the concrete type `T`, sharing the same value.
+    /// Otherwise an `Err` is returned with the same pointer that was passed in.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::any::Any;
+    /// use interior_mutability_pointer::{imp_dyn, Imp};
+    /// let p: Imp<dyn Any> = unsafe { imp_dyn!(5) };
+    /// let p = Imp::downcast::<String>(p).unwrap_err();
+    /// let mut p = Imp::downcast::<i32>(p).ok().unwrap();
+    /// p += 1;
+    /// assert_eq!(p, 6);
+    /// ```
+    #[track_caller]
+    pub fn downcast<T: Any>(this: Self) -> Result<Imp<T>, Self> {
+        if Self::is::<T>(&this) {
+            let v = Rc::into_raw(this.v) as *const RefCell<T>;
+            Ok(Imp {
+                v: unsafe { Rc::from_raw(v) },
+            })
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Immutably borrows the inner value as a `T`, or returns `None` if it is of another type.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::any::Any;
+    /// use interior_mutability_pointer::{imp_dyn, Imp};
+    /// let p: Imp<dyn Any> = unsafe { imp_dyn!(String::from("yo")) };
+    /// assert_eq!(*Imp::downcast_ref::<String>(&p).unwrap(), "yo");
+    /// assert!(Imp::downcast_ref::<i32>(&p).is_none());
+    /// ```
+    #[track_caller]
+    pub fn downcast_ref<T: Any>(this: &Self) -> Option<ImpRef<'_, T>> {
+        // The type is checked under the borrow, so the value is never looked at while it is mutably borrowed.
+        let r = match this.v.try_borrow() {
+            Ok(r) => Ok(Ref::filter_map(r, |v| v.as_any().downcast_ref::<T>()).ok()?),
+            Err(e) => Err(e),
+        };
+        Some(ImpRef::borrow_in(this.v.as_ptr() as *const (), r))
+    }
+
+    /// Mutably borrows the inner value as a `T`, or returns `None` if it is of another type.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn downcast_mut<T: Any>(this: &Self) -> Option<ImpRefMut<'_, T>> {
+        let r = match this.v.try_borrow_mut() {
+            Ok(r) => Ok(RefMut::filter_map(r, |v| v.as_any_mut().downcast_mut::<T>()).ok()?),
+            Err(e) => Err(e),
+        };
+        Some(ImpRefMut::borrow_in(this.v.as_ptr() as *const (), r))
+    }
+}
@@ -0,0 +1,94 @@
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+/// Compares and hashes a pointer by the allocation it points to, instead of by its value.
+///
+/// Two `ByPtr` are equal exactly when [`Imp::ptr_eq`](crate::Imp::ptr_eq) would return true,
+/// which allows building identity-keyed maps and sets of shared values,
+/// whose keys stay valid while the values are mutated.
+/// [`MappedImp`](crate::MappedImp) pointers are only equal to their clones,
+/// as the part they point to can move when the value is mutated.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashSet;
+/// use interior_mutability_pointer::{ByPtr, Imp};
+/// let p1 = unsafe { Imp::new(5) };
+/// let p2 = unsafe { Imp::new(5) };
+/// let mut set = HashSet::new();
+/// set.insert(ByPtr(p1.clone()));
+/// set.insert(ByPtr(p2));
+/// set.insert(ByPtr(p1)); // Already in the set, even though p2 holds the same value.
+/// assert_eq!(set.len(), 2);
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ByPtr<P>(pub P);
+
+impl<P> Deref for ByPtr<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P> DerefMut for ByPtr<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.0
+    }
+}
+
+/*
+    Implements the identity comparisons for a pointer type, given how to get its address.
+*/
+macro_rules! by_ptr_impl {
+    ($imp:ident, |$p:ident| $addr:expr) => {
+        impl<T: ?Sized> ByPtr<crate::$imp<T>> {
+            fn addr(this: &Self) -> usize {
+                let $p = &this.0;
+                $addr as *const () as usize
+            }
+        }
+
+        impl<T: ?Sized> PartialEq for ByPtr<crate::$imp<T>> {
+            fn eq(&self, other: &Self) -> bool {
+                Self::addr(self) == Self::addr(other)
+            }
+        }
+
+        impl<T: ?Sized> Eq for ByPtr<crate::$imp<T>> {}
+
+        impl<T: ?Sized> PartialOrd for ByPtr<crate::$imp<T>> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl<T: ?Sized> Ord for ByPtr<crate::$imp<T>> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                Self::addr(self).cmp(&Self::addr(other))
+            }
+        }
+
+        impl<T: ?Sized> Hash for ByPtr<crate::$imp<T>> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                Self::addr(self).hash(state)
+            }
+        }
+    };
+}
+
+by_ptr_impl!(Imp, |p| alloc::rc::Rc::as_ptr(&p.v));
+by_ptr_impl!(GuardedImp, |p| alloc::rc::Rc::as_ptr(&p.v));
+by_ptr_impl!(WeakImp, |p| p.v.as_ptr());
+// The part a MappedImp points to can move, so it is compared by its projection, shared by its clones.
+by_ptr_impl!(MappedImp, |p| crate::MappedImp::projection_ptr(p));
+by_ptr_impl!(ObservableImp, |p| crate::ObservableImp::as_ptr(p));
+by_ptr_impl!(HistoryImp, |p| crate::HistoryImp::as_ptr(p));
+#[cfg(feature = "std")]
+by_ptr_impl!(SyncImp, |p| crate::SyncImp::as_ptr(p));
+#[cfg(feature = "std")]
+by_ptr_impl!(MutexImp, |p| crate::MutexImp::as_ptr(p));
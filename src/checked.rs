@@ -0,0 +1,159 @@
+/*
+    Debug-build aliasing detector, enabled by the `checked` feature.
+
+    Every guard handed out by `read`/`write` records its access and call site per allocation until it is dropped.
+    `Deref` and `DerefMut` can not know how long the returned reference lives, so they check against the
+    recorded guards, panicking with both call sites when the accesses conflict.
+
+    `DerefMut` also records its call site and the pointer it went through, as outstanding until that pointer
+    is used again, which can only happen once the `&mut T` is gone. A `Deref` or `DerefMut` through another
+    pointer to the same value in the meantime is reported, which catches a `&mut T` kept alive across a `&T`
+    from a clone (issue #2). Taking a guard or cloning a pointer to the value ends the outstanding access too,
+    as a point where the value is known to be shared again.
+*/
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    panic::Location,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Access {
+    Shared,
+    Exclusive,
+}
+
+impl Access {
+    fn conflicts(self, other: Access) -> bool {
+        self == Access::Exclusive || other == Access::Exclusive
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Access::Shared => "shared",
+            Access::Exclusive => "exclusive",
+        }
+    }
+}
+
+struct Entry {
+    id: u64,
+    access: Access,
+    location: &'static Location<'static>,
+}
+
+// The last `DerefMut` of a value, with the address of the pointer it went through.
+struct DerefMut {
+    handle: usize,
+    location: &'static Location<'static>,
+}
+
+thread_local! {
+    static ACCESSES: RefCell<HashMap<usize, Vec<Entry>>> = RefCell::new(HashMap::new());
+    static DEREF_MUTS: RefCell<HashMap<usize, DerefMut>> = RefCell::new(HashMap::new());
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Panics if `access` at the caller's location conflicts with an outstanding access to `addr`.
+#[track_caller]
+pub(crate) fn check<T: ?Sized>(addr: *const T, access: Access) {
+    let addr = addr as *const () as usize;
+    let location = Location::caller();
+    let conflict = ACCESSES.with(|a| {
+        a.borrow().get(&addr).and_then(|entries| {
+            entries
+                .iter()
+                .find(|e| access.conflicts(e.access))
+                .map(|e| (e.access, e.location))
+        })
+    });
+    if let Some((other, other_location)) = conflict {
+        panic!(
+            "conflicting access to shared value: {} access at {} overlaps {} access at {}",
+            access.name(),
+            location,
+            other.name(),
+            other_location
+        );
+    }
+}
+
+/// Checks a `Deref` or `DerefMut` of `addr` through the pointer at `handle`, like [`check`],
+/// and panics if it overlaps an outstanding `DerefMut` through another pointer.
+#[track_caller]
+pub(crate) fn deref<T: ?Sized>(addr: *const T, handle: *const (), access: Access) {
+    check(addr, access);
+    let addr = addr as *const () as usize;
+    let handle = handle as usize;
+    let location = Location::caller();
+    let outstanding = DEREF_MUTS.with(|d| {
+        let mut d = d.borrow_mut();
+        let other = match d.remove(&addr) {
+            Some(e) if e.handle != handle => Some(e.location),
+            _ => None,
+        };
+        if other.is_none() && access == Access::Exclusive {
+            d.insert(addr, DerefMut { handle, location });
+        }
+        other
+    });
+    if let Some(other_location) = outstanding {
+        panic!(
+            "conflicting access to shared value: {} access at {} overlaps exclusive access at {} through another pointer",
+            access.name(),
+            location,
+            other_location
+        );
+    }
+}
+
+/// Ends the outstanding `DerefMut` of `addr`, if any.
+pub(crate) fn release<T: ?Sized>(addr: *const T) {
+    let addr = addr as *const () as usize;
+    let _ = DEREF_MUTS.try_with(|d| d.borrow_mut().remove(&addr));
+}
+
+/// An access recorded for as long as the guard holding it is alive.
+pub(crate) struct Tracked {
+    addr: usize,
+    id: u64,
+}
+
+impl Tracked {
+    /// Records an access to `addr` at the caller's location, panicking if it conflicts with an outstanding one.
+    #[track_caller]
+    pub(crate) fn acquire<T: ?Sized>(addr: *const T, access: Access) -> Self {
+        check(addr, access);
+        release(addr);
+        let addr = addr as *const () as usize;
+        let location = Location::caller();
+        let id = NEXT_ID.with(|n| {
+            let id = n.get();
+            n.set(id + 1);
+            id
+        });
+        ACCESSES.with(|a| {
+            a.borrow_mut().entry(addr).or_default().push(Entry {
+                id,
+                access,
+                location,
+            })
+        });
+        Self { addr, id }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        // The thread local may already be gone if a guard is dropped during thread teardown.
+        let _ = ACCESSES.try_with(|a| {
+            let mut a = a.borrow_mut();
+            if let Some(entries) = a.get_mut(&self.addr) {
+                entries.retain(|e| e.id != self.id);
+                if entries.is_empty() {
+                    a.remove(&self.addr);
+                }
+            }
+        });
+    }
+}
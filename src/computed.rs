@@ -0,0 +1,247 @@
+/*
+    Values derived from observable pointers, recomputed lazily when one of the values they read has changed.
+
+    Evaluation is pull-based: reading a computed value first brings the values it depends on up to date,
+    in the order they were read, and only then checks their versions. So across diamond dependencies
+    every value is recomputed at most once per change, and never sees a mix of old and new inputs.
+    A thread-local epoch, bumped by every change to an ObservableImp, lets values that were already
+    checked since the last change skip walking their dependencies.
+*/
+use std::{
+    boxed::Box,
+    cell::{BorrowError, Cell, Ref, RefCell},
+    rc::Rc,
+    vec::Vec,
+};
+
+use crate::ImpRef;
+
+thread_local! {
+    static EPOCH: Cell<u64> = const { Cell::new(0) };
+    // The values read by each computed value currently being evaluated, innermost last.
+    static TRACKING: RefCell<Vec<Vec<Dep>>> = const { RefCell::new(Vec::new()) };
+}
+
+/*
+    Something a computed value can depend on.
+*/
+trait Source {
+    // Brings the source up to date, and returns its version.
+    fn version(&self) -> u64;
+}
+
+// The version counter of an ObservableImp.
+impl Source for Cell<u64> {
+    fn version(&self) -> u64 {
+        self.get()
+    }
+}
+
+struct Dep {
+    source: Rc<dyn Source>,
+    seen: u64,
+}
+
+/*
+    Called on every change to an observable value.
+*/
+pub(crate) fn changed() {
+    EPOCH.with(|e| e.set(e.get() + 1));
+}
+
+/*
+    Records a read of `version` by the computed value being evaluated, if any.
+*/
+pub(crate) fn track(version: &Rc<Cell<u64>>) {
+    record(version.clone(), version.get());
+}
+
+fn record(source: Rc<dyn Source>, seen: u64) {
+    TRACKING.with(|t| {
+        if let Some(deps) = t.borrow_mut().last_mut() {
+            let addr = Rc::as_ptr(&source) as *const ();
+            if !deps
+                .iter()
+                .any(|d| Rc::as_ptr(&d.source) as *const () == addr)
+            {
+                deps.push(Dep { source, seen });
+            }
+        }
+    });
+}
+
+/// A value derived from [`ObservableImp`](crate::ObservableImp) pointers and other `Computed` values,
+/// recomputed when one of them has changed.
+///
+/// The values it depends on are found by tracking which ones the closure reads while it runs,
+/// so they can differ between evaluations. The closure is only run when the value is read,
+/// and at most once per change, also when several paths lead to the same source.
+/// The closure should not mutate the values it reads.
+///
+/// Requires the `std` feature.
+///
+/// # Examples
+/// ```
+/// use interior_mutability_pointer::{Computed, ObservableImp};
+/// let mut width = unsafe { ObservableImp::new(2) };
+/// let height = unsafe { ObservableImp::new(3) };
+/// let (w, h) = (width.clone(), height.clone());
+/// let area = Computed::new(move || *w * *h);
+/// assert_eq!(Computed::get(&area), 6);
+/// width += 1;
+/// assert_eq!(Computed::get(&area), 9);
+/// ```
+pub struct Computed<U> {
+    v: Rc<Inner<U>>,
+}
+
+struct Inner<U> {
+    f: Box<dyn Fn() -> U>,
+    value: RefCell<Option<U>>,
+    deps: RefCell<Vec<Dep>>,
+    version: Cell<u64>,
+    // The epoch at which the value was last known to be up to date.
+    verified: Cell<Option<u64>>,
+    evaluating: Cell<bool>,
+}
+
+impl<U> Inner<U> {
+    fn refresh(&self) {
+        let epoch = EPOCH.with(Cell::get);
+        if self.verified.get() == Some(epoch) {
+            return;
+        }
+        let stale = self.value.borrow().is_none()
+            || self
+                .deps
+                .borrow()
+                .iter()
+                .any(|d| d.source.version() != d.seen);
+        if stale {
+            self.recompute();
+        }
+        self.verified.set(Some(epoch));
+    }
+
+    fn recompute(&self) {
+        struct Frame<'a>(&'a Cell<bool>);
+        impl Drop for Frame<'_> {
+            fn drop(&mut self) {
+                self.0.set(false);
+                TRACKING.with(|t| t.borrow_mut().pop());
+            }
+        }
+
+        assert!(!self.evaluating.get(), "computed value depends on itself");
+        self.evaluating.set(true);
+        TRACKING.with(|t| t.borrow_mut().push(Vec::new()));
+        let (value, deps) = {
+            let _frame = Frame(&self.evaluating);
+            let value = (self.f)();
+            let deps = TRACKING.with(|t| core::mem::take(t.borrow_mut().last_mut().unwrap()));
+            (value, deps)
+        };
+        *self
+            .value
+            .try_borrow_mut()
+            .expect("computed value changed while it is borrowed") = Some(value);
+        *self.deps.borrow_mut() = deps;
+        self.version.set(self.version.get() + 1);
+    }
+}
+
+impl<U> Source for Inner<U> {
+    fn version(&self) -> u64 {
+        self.refresh();
+        self.version.get()
+    }
+}
+
+impl<U: 'static> Computed<U> {
+    /// Returns a value computed by `f`, which is first run when the value is read.
+    ///
+    /// # Arguments
+    /// * `f` - Computes the value, reading the values it depends on
+    pub fn new(f: impl Fn() -> U + 'static) -> Self {
+        Self {
+            v: Rc::new(Inner {
+                f: Box::new(f),
+                value: RefCell::new(None),
+                deps: RefCell::new(Vec::new()),
+                version: Cell::new(0),
+                verified: Cell::new(None),
+                evaluating: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Borrows the value, recomputing it first if one of the values it depends on has changed.
+    ///
+    /// # Panics
+    /// Panics if the value depends on itself, or if it has to be recomputed while an earlier guard is still alive.
+    #[track_caller]
+    pub fn read(this: &Self) -> ImpRef<'_, U> {
+        match Self::try_read(this) {
+            Ok(r) => r,
+            Err(e) => panic!("already mutably borrowed: {:?}", e),
+        }
+    }
+
+    /// Borrows the value like [`Computed::read`], returning an error if it is being recomputed.
+    #[track_caller]
+    pub fn try_read(this: &Self) -> Result<ImpRef<'_, U>, BorrowError> {
+        this.v.refresh();
+        record(this.v.clone(), this.v.version.get());
+        let value = this
+            .v
+            .value
+            .try_borrow()
+            .map(|r| Ref::map(r, |v| v.as_ref().unwrap()));
+        ImpRef::try_borrow_in(this.v.value.as_ptr() as *const (), value)
+    }
+
+    /// Returns a clone of the value, recomputing it first if one of the values it depends on has changed.
+    #[track_caller]
+    pub fn get(this: &Self) -> U
+    where
+        U: Clone,
+    {
+        Self::read(this).clone()
+    }
+
+    /// Returns the number of times the value has been computed.
+    ///
+    /// Brings the value up to date first, so a changed version means the value was recomputed.
+    pub fn version(this: &Self) -> u64 {
+        this.v.refresh();
+        this.v.version.get()
+    }
+}
+
+/*
+    Implements cloning the pointer, sharing the value and its dependencies.
+*/
+mod clone_impl {
+    use super::Computed;
+
+    impl<U> Clone for Computed<U> {
+        fn clone(&self) -> Self {
+            Self { v: self.v.clone() }
+        }
+    }
+}
+
+/*
+    Allows printing the current value.
+*/
+mod debug_impl {
+    use core::fmt::Debug;
+
+    use super::Computed;
+
+    impl<U: Debug + 'static> Debug for Computed<U> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            Computed::read(self).fmt(f)
+        }
+    }
+}
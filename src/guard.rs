@@ -0,0 +1,307 @@
+use alloc::rc::Rc;
+use core::cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut};
+
+#[cfg(feature = "checked")]
+use crate::checked::{Access, Tracked};
+use crate::Imp;
+
+/// A shared borrow of the value behind an [`Imp`] or [`GuardedImp`].
+///
+/// Holds the real `Ref` of the inner `RefCell`, so the borrow is tracked
+/// until the guard is dropped.
+pub struct ImpRef<'a, T: ?Sized> {
+    r: Ref<'a, T>,
+    #[cfg(feature = "checked")]
+    _tracked: Tracked,
+}
+
+/// An exclusive borrow of the value behind an [`Imp`] or [`GuardedImp`].
+///
+/// Holds the real `RefMut` of the inner `RefCell`, so the borrow is tracked
+/// until the guard is dropped.
+pub struct ImpRefMut<'a, T: ?Sized> {
+    r: RefMut<'a, T>,
+    #[cfg(feature = "checked")]
+    _tracked: Tracked,
+}
+
+impl<'a, T: ?Sized> ImpRef<'a, T> {
+    #[track_caller]
+    pub(crate) fn borrow(cell: &'a RefCell<T>) -> Self {
+        #[cfg(feature = "checked")]
+        let tracked = Tracked::acquire(cell.as_ptr(), Access::Shared);
+        Self {
+            r: cell.borrow(),
+            #[cfg(feature = "checked")]
+            _tracked: tracked,
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn try_borrow(cell: &'a RefCell<T>) -> Result<Self, BorrowError> {
+        Ok(Self {
+            r: cell.try_borrow()?,
+            #[cfg(feature = "checked")]
+            _tracked: Tracked::acquire(cell.as_ptr(), Access::Shared),
+        })
+    }
+
+    /*
+        Wraps a borrow of a value inside the allocation at `root`, tracking it as an access to the whole allocation.
+    */
+    #[track_caller]
+    #[cfg_attr(not(feature = "checked"), allow(unused_variables))]
+    pub(crate) fn borrow_in(root: *const (), r: Result<Ref<'a, T>, BorrowError>) -> Self {
+        #[cfg(feature = "checked")]
+        let tracked = Tracked::acquire(root, Access::Shared);
+        match r {
+            Ok(r) => Self {
+                r,
+                #[cfg(feature = "checked")]
+                _tracked: tracked,
+            },
+            Err(e) => panic!("already mutably borrowed: {:?}", e),
+        }
+    }
+
+    #[track_caller]
+    #[cfg_attr(not(feature = "checked"), allow(unused_variables))]
+    pub(crate) fn try_borrow_in(
+        root: *const (),
+        r: Result<Ref<'a, T>, BorrowError>,
+    ) -> Result<Self, BorrowError> {
+        Ok(Self {
+            r: r?,
+            #[cfg(feature = "checked")]
+            _tracked: Tracked::acquire(root, Access::Shared),
+        })
+    }
+}
+
+impl<'a, T: ?Sized> ImpRefMut<'a, T> {
+    #[track_caller]
+    pub(crate) fn borrow(cell: &'a RefCell<T>) -> Self {
+        #[cfg(feature = "checked")]
+        let tracked = Tracked::acquire(cell.as_ptr(), Access::Exclusive);
+        Self {
+            r: cell.borrow_mut(),
+            #[cfg(feature = "checked")]
+            _tracked: tracked,
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn try_borrow(cell: &'a RefCell<T>) -> Result<Self, BorrowMutError> {
+        Ok(Self {
+            r: cell.try_borrow_mut()?,
+            #[cfg(feature = "checked")]
+            _tracked: Tracked::acquire(cell.as_ptr(), Access::Exclusive),
+        })
+    }
+
+    #[track_caller]
+    #[cfg_attr(not(feature = "checked"), allow(unused_variables))]
+    pub(crate) fn borrow_in(root: *const (), r: Result<RefMut<'a, T>, BorrowMutError>) -> Self {
+        #[cfg(feature = "checked")]
+        let tracked = Tracked::acquire(root, Access::Exclusive);
+        match r {
+            Ok(r) => Self {
+                r,
+                #[cfg(feature = "checked")]
+                _tracked: tracked,
+            },
+            Err(e) => panic!("already borrowed: {:?}", e),
+        }
+    }
+
+    #[track_caller]
+    #[cfg_attr(not(feature = "checked"), allow(unused_variables))]
+    pub(crate) fn try_borrow_in(
+        root: *const (),
+        r: Result<RefMut<'a, T>, BorrowMutError>,
+    ) -> Result<Self, BorrowMutError> {
+        Ok(Self {
+            r: r?,
+            #[cfg(feature = "checked")]
+            _tracked: Tracked::acquire(root, Access::Exclusive),
+        })
+    }
+}
+
+/// A pointer sharing its value like [`Imp`], but only giving access to it through guards.
+///
+/// As every access goes through the borrow flag of the inner `RefCell`,
+/// `GuardedImp` can be constructed safely. It still supports the same operators as [`Imp`].
+pub struct GuardedImp<T: ?Sized> {
+    pub(crate) v: Rc<RefCell<T>>,
+}
+
+impl<T> GuardedImp<T> {
+    /// Returns a guarded pointer to the data
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The value to be pointed to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use interior_mutability_pointer::GuardedImp;
+    /// let p = GuardedImp::new(String::new());
+    /// let p2 = p.clone();
+    /// p.write().push_str("yoo"); // Modifies the inner value of both p and p2.
+    /// assert_eq!(*p2.read(), "yoo");
+    /// ```
+    pub fn new(t: T) -> Self {
+        Self {
+            v: Rc::new(RefCell::new(t)),
+        }
+    }
+}
+
+impl<T: ?Sized> GuardedImp<T> {
+    /// Immutably borrows the inner value.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    /// For a non-panicking variant, use [`GuardedImp::try_read`].
+    #[track_caller]
+    pub fn read(&self) -> ImpRef<'_, T> {
+        ImpRef::borrow(&self.v)
+    }
+
+    /// Mutably borrows the inner value.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    /// For a non-panicking variant, use [`GuardedImp::try_write`].
+    #[track_caller]
+    pub fn write(&self) -> ImpRefMut<'_, T> {
+        ImpRefMut::borrow(&self.v)
+    }
+
+    /// Immutably borrows the inner value, returning an error if the value is currently mutably borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::GuardedImp;
+    /// let p = GuardedImp::new(5);
+    /// let w = p.write();
+    /// assert!(p.try_read().is_err());
+    /// drop(w);
+    /// assert!(p.try_read().is_ok());
+    /// ```
+    #[track_caller]
+    pub fn try_read(&self) -> Result<ImpRef<'_, T>, BorrowError> {
+        ImpRef::try_borrow(&self.v)
+    }
+
+    /// Mutably borrows the inner value, returning an error if the value is currently borrowed.
+    #[track_caller]
+    pub fn try_write(&self) -> Result<ImpRefMut<'_, T>, BorrowMutError> {
+        ImpRefMut::try_borrow(&self.v)
+    }
+
+    /// Returns true if two pointers are equal
+    ///
+    /// # Arguments
+    /// * `this` - A pointer to compare
+    /// * `other` - The other pointer to compare to
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Rc::ptr_eq(&this.v, &other.v)
+    }
+
+    /// Turns the pointer into an [`Imp`] sharing the same value.
+    ///
+    /// # Safety
+    /// The returned `Imp` gives unchecked access to the value through `Deref` and `DerefMut`,
+    /// see [`Imp::new`].
+    pub unsafe fn into_imp(this: Self) -> Imp<T> {
+        Imp { v: this.v }
+    }
+}
+
+/*
+    Implements cloning the pointer.
+*/
+mod clone_impl {
+    use super::GuardedImp;
+
+    impl<T: ?Sized> Clone for GuardedImp<T> {
+        fn clone(&self) -> Self {
+            Self { v: self.v.clone() }
+        }
+    }
+}
+
+/*
+    Allows turning an Imp into a GuardedImp, dropping the unchecked access.
+*/
+mod from_impl {
+    use super::GuardedImp;
+    use crate::Imp;
+
+    impl<T: ?Sized> From<Imp<T>> for GuardedImp<T> {
+        fn from(imp: Imp<T>) -> Self {
+            Self { v: imp.v }
+        }
+    }
+}
+
+/*
+    Allows access to the inner value through the guards.
+*/
+mod deref_impl {
+    use core::{
+        fmt::{Debug, Display},
+        ops::{Deref, DerefMut},
+    };
+
+    use super::{ImpRef, ImpRefMut};
+
+    impl<T: ?Sized> Deref for ImpRef<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.r
+        }
+    }
+
+    impl<T: ?Sized> Deref for ImpRefMut<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.r
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for ImpRefMut<'_, T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.r
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for ImpRef<'_, T> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            self.r.fmt(f)
+        }
+    }
+
+    impl<T: ?Sized + Display> Display for ImpRef<'_, T> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            self.r.fmt(f)
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for ImpRefMut<'_, T> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            self.r.fmt(f)
+        }
+    }
+
+    impl<T: ?Sized + Display> Display for ImpRefMut<'_, T> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            self.r.fmt(f)
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc};
+use core::{
+    cell::{BorrowError, BorrowMutError, Cell, RefCell},
+    mem,
+};
+
+use crate::{ImpRef, ImpRefMut};
+
+/// A pointer like [`Imp`](crate::Imp), which keeps earlier versions of its value to step back and forth between.
+///
+/// Every mutable access, through `DerefMut`, `IndexMut`, [`HistoryImp::write`] or the compound assignment
+/// operators, records a checkpoint of the value as it was before. [`HistoryImp::undo`] goes back to the last
+/// checkpoint and [`HistoryImp::redo`] forward again, for all pointers to the value.
+/// With [`HistoryImp::set_auto`] turned off only [`HistoryImp::checkpoint`] records them.
+///
+/// The history is unbounded by default, see [`HistoryImp::limit_depth`] and [`HistoryImp::limit_size`].
+///
+/// # Examples
+/// ```
+/// use interior_mutability_pointer::HistoryImp;
+/// let mut doc = unsafe { HistoryImp::new(String::from("a")) };
+/// let p = doc.clone();
+/// doc.push('b');
+/// doc.push('c');
+/// assert!(HistoryImp::undo(&p));
+/// assert_eq!(*doc, "ab");
+/// assert!(HistoryImp::redo(&p));
+/// assert_eq!(*doc, "abc");
+/// ```
+pub struct HistoryImp<T: ?Sized> {
+    v: Rc<History<T>>,
+}
+
+struct Snapshot<T: ?Sized> {
+    value: Box<T>,
+    size: usize,
+}
+
+struct History<T: ?Sized> {
+    // Function pointers made in `new`, so the pointer type itself does not require T: Clone.
+    snapshot: fn(&T) -> Box<T>,
+    swap: fn(&mut T, &mut T),
+    size: Cell<fn(&T) -> usize>,
+    auto: Cell<bool>,
+    depth: Cell<usize>,
+    bytes: Cell<usize>,
+    // Oldest first, the next one to step to is at the back.
+    undo: RefCell<VecDeque<Snapshot<T>>>,
+    redo: RefCell<VecDeque<Snapshot<T>>>,
+    value: RefCell<T>,
+}
+
+impl<T: ?Sized> History<T> {
+    /*
+        Called before every mutable access.
+    */
+    fn changing(&self, t: &T) {
+        if self.auto.get() {
+            self.checkpoint(t);
+        }
+    }
+
+    fn checkpoint(&self, t: &T) {
+        let value = (self.snapshot)(t);
+        let size = (self.size.get())(&value);
+        self.redo.borrow_mut().clear();
+        self.undo.borrow_mut().push_back(Snapshot { value, size });
+        self.trim();
+    }
+
+    /*
+        Swaps the value with the last snapshot in `from`, which is then kept in `to`.
+    */
+    #[track_caller]
+    fn step(
+        &self,
+        from: &RefCell<VecDeque<Snapshot<T>>>,
+        to: &RefCell<VecDeque<Snapshot<T>>>,
+    ) -> bool {
+        let mut value = ImpRefMut::borrow(&self.value);
+        let Some(mut s) = from.borrow_mut().pop_back() else {
+            return false;
+        };
+        (self.swap)(&mut value, &mut s.value);
+        s.size = (self.size.get())(&s.value);
+        to.borrow_mut().push_back(s);
+        drop(value);
+        self.trim();
+        true
+    }
+
+    /*
+        Drops the oldest snapshots until the limits are met, then the redo snapshots furthest away.
+        The last redo snapshot is always kept, so an undo can be taken back right away.
+    */
+    fn trim(&self) {
+        let mut undo = self.undo.borrow_mut();
+        let mut redo = self.redo.borrow_mut();
+        while undo.len() > self.depth.get() {
+            undo.pop_front();
+        }
+        let mut total: usize = undo.iter().chain(redo.iter()).map(|s| s.size).sum();
+        while total > self.bytes.get() {
+            let s = match undo.pop_front() {
+                Some(s) => s,
+                None if redo.len() > 1 => redo.pop_front().unwrap(),
+                None => break,
+            };
+            total -= s.size;
+        }
+    }
+}
+
+impl<T: Clone> HistoryImp<T> {
+    /// Returns a pointer to the data, with an empty history.
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The value to be pointed to.
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`](crate::Imp::new).
+    pub unsafe fn new(t: T) -> Self {
+        Self {
+            v: Rc::new(History {
+                snapshot: |t| Box::new(t.clone()),
+                swap: mem::swap::<T>,
+                size: Cell::new(|_| mem::size_of::<T>()),
+                auto: Cell::new(true),
+                depth: Cell::new(usize::MAX),
+                bytes: Cell::new(usize::MAX),
+                undo: RefCell::new(VecDeque::new()),
+                redo: RefCell::new(VecDeque::new()),
+                value: RefCell::new(t),
+            }),
+        }
+    }
+}
+
+impl<T: ?Sized> HistoryImp<T> {
+    /// Records the current value as a checkpoint to return to with [`HistoryImp::undo`],
+    /// and forgets the checkpoints that could be returned to with [`HistoryImp::redo`].
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn checkpoint(this: &Self) {
+        let value = ImpRef::borrow(&this.v.value);
+        this.v.checkpoint(&value);
+    }
+
+    /// Sets whether every mutable access records a checkpoint, which it does by default.
+    /// When turned off, checkpoints are only recorded by [`HistoryImp::checkpoint`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::HistoryImp;
+    /// let mut doc = unsafe { HistoryImp::new(vec![1]) };
+    /// HistoryImp::set_auto(&doc, false);
+    /// HistoryImp::checkpoint(&doc);
+    /// doc.push(2);
+    /// doc.push(3);
+    /// HistoryImp::undo(&doc);
+    /// assert_eq!(doc, vec![1]);
+    /// ```
+    pub fn set_auto(this: &Self, auto: bool) {
+        this.v.auto.set(auto);
+    }
+
+    /// Keeps at most `depth` checkpoints to undo to, dropping the oldest ones first.
+    pub fn limit_depth(this: &Self, depth: usize) {
+        this.v.depth.set(depth);
+        this.v.trim();
+    }
+
+    /// Keeps the checkpoints to undo and redo to at a total of at most `bytes`,
+    /// dropping the oldest ones first.
+    /// The checkpoint to [redo](HistoryImp::redo) to next is always kept, even when it does not fit.
+    ///
+    /// # Arguments
+    /// * `this` - The pointer to the value
+    /// * `bytes` - The most memory to use for the history
+    /// * `size` - Returns the memory used by a checkpoint, including what it owns on the heap
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::HistoryImp;
+    /// let mut doc = unsafe { HistoryImp::new(String::new()) };
+    /// HistoryImp::limit_size(&doc, 7, String::len);
+    /// for c in "abcde".chars() {
+    ///     doc.push(c);
+    /// }
+    /// // Only the checkpoints "abc" and "abcd" fit.
+    /// assert_eq!(HistoryImp::undo_len(&doc), 2);
+    /// ```
+    pub fn limit_size(this: &Self, bytes: usize, size: fn(&T) -> usize) {
+        this.v.size.set(size);
+        this.v.bytes.set(bytes);
+        for s in this
+            .v
+            .undo
+            .borrow_mut()
+            .iter_mut()
+            .chain(this.v.redo.borrow_mut().iter_mut())
+        {
+            s.size = size(&s.value);
+        }
+        this.v.trim();
+    }
+
+    /// Returns the value to the last checkpoint, keeping the current one to [redo](HistoryImp::redo).
+    /// Returns false if there is no checkpoint to return to.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn undo(this: &Self) -> bool {
+        this.v.step(&this.v.undo, &this.v.redo)
+    }
+
+    /// Takes back the last [undo](HistoryImp::undo).
+    /// Returns false if there is nothing to redo, which is also the case after a new checkpoint.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn redo(this: &Self) -> bool {
+        this.v.step(&this.v.redo, &this.v.undo)
+    }
+
+    /// Returns the number of checkpoints that can be returned to with [`HistoryImp::undo`].
+    pub fn undo_len(this: &Self) -> usize {
+        this.v.undo.borrow().len()
+    }
+
+    /// Returns the number of undos that can be taken back with [`HistoryImp::redo`].
+    pub fn redo_len(this: &Self) -> usize {
+        this.v.redo.borrow().len()
+    }
+
+    /// Forgets all checkpoints.
+    pub fn clear(this: &Self) {
+        this.v.undo.borrow_mut().clear();
+        this.v.redo.borrow_mut().clear();
+    }
+
+    /// Immutably borrows the inner value, going through the borrow flag of the inner `RefCell`.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn read(this: &Self) -> ImpRef<'_, T> {
+        ImpRef::borrow(&this.v.value)
+    }
+
+    /// Mutably borrows the inner value, recording a checkpoint of it first.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn write(this: &Self) -> ImpRefMut<'_, T> {
+        let r = ImpRefMut::borrow(&this.v.value);
+        this.v.changing(&r);
+        r
+    }
+
+    /// Immutably borrows the inner value, returning an error if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn try_read(this: &Self) -> Result<ImpRef<'_, T>, BorrowError> {
+        ImpRef::try_borrow(&this.v.value)
+    }
+
+    /// Mutably borrows the inner value like [`HistoryImp::write`],
+    /// returning an error if the value is currently borrowed.
+    #[track_caller]
+    pub fn try_write(this: &Self) -> Result<ImpRefMut<'_, T>, BorrowMutError> {
+        let r = ImpRefMut::try_borrow(&this.v.value)?;
+        this.v.changing(&r);
+        Ok(r)
+    }
+
+    /// Returns true if two pointers point to the same value.
+    ///
+    /// # Arguments
+    /// * `this` - A pointer to compare
+    /// * `other` - The other pointer to compare to
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Rc::ptr_eq(&this.v, &other.v)
+    }
+
+    pub(crate) fn as_ptr(this: &Self) -> *const T {
+        this.v.value.as_ptr()
+    }
+}
+
+/*
+    Implements cloning the pointer, sharing the value and its history.
+*/
+mod clone_impl {
+    use super::HistoryImp;
+
+    impl<T: ?Sized> Clone for HistoryImp<T> {
+        fn clone(&self) -> Self {
+            #[cfg(feature = "checked")]
+            crate::checked::release(self.v.value.as_ptr());
+            Self { v: self.v.clone() }
+        }
+    }
+}
+
+/*
+    Allows access to the inner methods from T, the same way as Imp.
+    DerefMut records a checkpoint, as the value may be changed through the returned reference.
+*/
+mod deref_impl {
+    use core::ops::{Deref, DerefMut};
+
+    use super::HistoryImp;
+
+    impl<T: ?Sized> Deref for HistoryImp<T> {
+        type Target = T;
+
+        #[track_caller]
+        fn deref(&self) -> &Self::Target {
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.v.value.as_ptr(),
+                self as *const Self as *const (),
+                crate::checked::Access::Shared,
+            );
+            unsafe { &*self.v.value.as_ptr() }
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for HistoryImp<T> {
+        #[track_caller]
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.v.value.as_ptr(),
+                self as *const Self as *const (),
+                crate::checked::Access::Exclusive,
+            );
+            self.v.changing(unsafe { &*self.v.value.as_ptr() });
+            unsafe { &mut *self.v.value.as_ptr() }
+        }
+    }
+}
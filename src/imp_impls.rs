@@ -1,122 +1,977 @@
+use alloc::rc::Rc;
+use core::{
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{GuardedImp, HistoryImp, Imp, MappedImp, ObservableImp};
+#[cfg(feature = "std")]
+use crate::{MutexImp, SyncImp};
+
 /*
-    Allows using != and ==.
+    Gives the impls in this file uniform access to the value behind every handle type.
 */
-mod eq_partial_eq_impl {
-    use std::ops::Deref;
+pub(crate) trait Handle {
+    type Target: ?Sized;
+
+    fn shared(&self) -> impl Deref<Target = Self::Target> + '_;
+    fn exclusive(&self) -> impl DerefMut<Target = Self::Target> + '_;
+    fn addr(&self) -> *const ();
+
+    /*
+        Gives f shared access to both values at once.
+        Locks are taken once when both point to the same value, and otherwise in address order,
+        so comparing handles can not deadlock.
+    */
+    #[track_caller]
+    fn with_pair<R>(&self, other: &Self, f: impl FnOnce(&Self::Target, &Self::Target) -> R) -> R {
+        if self.addr() == other.addr() {
+            let a = self.shared();
+            f(&a, &a)
+        } else if self.addr() < other.addr() {
+            let a = self.shared();
+            let b = other.shared();
+            f(&a, &b)
+        } else {
+            let b = other.shared();
+            let a = self.shared();
+            f(&a, &b)
+        }
+    }
+}
 
-    use crate::Imp;
+impl<T: ?Sized> Handle for Imp<T> {
+    type Target = T;
 
-    impl<T: PartialEq> PartialEq for Imp<T> {
-        fn eq(&self, other: &Self) -> bool {
-            *self.deref() == *other.deref()
-        }
+    #[track_caller]
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        Imp::read(self)
+    }
+
+    #[track_caller]
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        Imp::write(self)
+    }
+
+    fn addr(&self) -> *const () {
+        self.v.as_ptr() as *const ()
+    }
+}
+
+impl<T: ?Sized> Handle for GuardedImp<T> {
+    type Target = T;
+
+    #[track_caller]
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        self.read()
+    }
+
+    #[track_caller]
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        self.write()
+    }
+
+    fn addr(&self) -> *const () {
+        self.v.as_ptr() as *const ()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ?Sized> Handle for SyncImp<T> {
+    type Target = T;
+
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        self.read()
+    }
+
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        self.write()
+    }
+
+    fn addr(&self) -> *const () {
+        SyncImp::as_ptr(self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ?Sized> Handle for MutexImp<T> {
+    type Target = T;
+
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        self.lock()
+    }
+
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        self.lock()
+    }
+
+    fn addr(&self) -> *const () {
+        MutexImp::as_ptr(self)
+    }
+}
+
+impl<T: ?Sized> Handle for MappedImp<T> {
+    type Target = T;
+
+    #[track_caller]
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        MappedImp::read(self)
+    }
+
+    #[track_caller]
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        MappedImp::write(self)
+    }
+
+    fn addr(&self) -> *const () {
+        MappedImp::as_ptr(self) as *const ()
+    }
+}
+
+impl<T: ?Sized> Handle for ObservableImp<T> {
+    type Target = T;
+
+    #[track_caller]
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        ObservableImp::read(self)
+    }
+
+    #[track_caller]
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        ObservableImp::write(self)
+    }
+
+    fn addr(&self) -> *const () {
+        ObservableImp::as_ptr(self) as *const ()
+    }
+}
+
+impl<T: ?Sized> Handle for HistoryImp<T> {
+    type Target = T;
+
+    #[track_caller]
+    fn shared(&self) -> impl Deref<Target = T> + '_ {
+        HistoryImp::read(self)
+    }
+
+    #[track_caller]
+    fn exclusive(&self) -> impl DerefMut<Target = T> + '_ {
+        HistoryImp::write(self)
     }
 
-    impl<T: PartialEq> PartialEq<T> for Imp<T> {
-        fn eq(&self, other: &T) -> bool {
-            let a = self.deref();
-            let b = other.deref();
-            a.eq(b)
+    fn addr(&self) -> *const () {
+        HistoryImp::as_ptr(self) as *const ()
+    }
+}
+
+/// Opts a type into operators with a primitive right-hand side, like `Imp<Vec3> * f32`.
+///
+/// A blanket `Imp<T> op U` would overlap with `Imp<T> op T`, so these operators are only implemented
+/// for the inner types implementing this trait, for every primitive `U` that `T op U` is implemented for.
+/// The value is cloned out of the pointer like for the other operators, so `T` also has to be `Clone`.
+///
+/// # Examples
+/// ```
+/// use core::ops::{Mul, MulAssign};
+/// use interior_mutability_pointer::{Imp, MixedOps};
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Vec3(f32, f32, f32);
+/// impl Mul<f32> for Vec3 {
+///     type Output = Vec3;
+///     fn mul(self, k: f32) -> Vec3 {
+///         Vec3(self.0 * k, self.1 * k, self.2 * k)
+///     }
+/// }
+/// impl MulAssign<f32> for Vec3 {
+///     fn mul_assign(&mut self, k: f32) {
+///         *self = *self * k;
+///     }
+/// }
+/// impl MixedOps for Vec3 {}
+///
+/// let mut v = unsafe { Imp::new(Vec3(1.0, 2.0, 3.0)) };
+/// assert_eq!(v.clone() * 2.0, Vec3(2.0, 4.0, 6.0));
+/// v *= 3.0;
+/// assert_eq!(v, Vec3(3.0, 6.0, 9.0));
+/// ```
+pub trait MixedOps {}
+
+/*
+    Lets the operator impls put their result in a new handle of the same type.
+    Kept apart from Handle, as a new MappedImp can only be made for 'static values.
+*/
+pub(crate) trait Wrap<T> {
+    fn wrap(t: T) -> Self;
+}
+
+impl<T> Wrap<T> for Imp<T> {
+    fn wrap(t: T) -> Self {
+        unsafe { Imp::new(t) }
+    }
+}
+
+impl<T> Wrap<T> for GuardedImp<T> {
+    fn wrap(t: T) -> Self {
+        GuardedImp::new(t)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Wrap<T> for SyncImp<T> {
+    fn wrap(t: T) -> Self {
+        SyncImp::new(t)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Wrap<T> for MutexImp<T> {
+    fn wrap(t: T) -> Self {
+        MutexImp::new(t)
+    }
+}
+
+impl<T> Wrap<T> for ObservableImp<T> {
+    fn wrap(t: T) -> Self {
+        unsafe { ObservableImp::new(t) }
+    }
+}
+
+impl<T: Clone> Wrap<T> for HistoryImp<T> {
+    fn wrap(t: T) -> Self {
+        unsafe { HistoryImp::new(t) }
+    }
+}
+
+impl<T: 'static> Wrap<T> for MappedImp<T> {
+    fn wrap(t: T) -> Self {
+        MappedImp::project(Rc::new(RefCell::new(t)), |t| t, |t| t)
+    }
+}
+
+/*
+    Expands an impl macro once for every handle type sharing the impls in this file.
+*/
+macro_rules! for_each_handle {
+    ($m:ident $(, $args:tt)*) => {
+        $m!(Imp $(, $args)*);
+        $m!(GuardedImp $(, $args)*);
+        $m!(MappedImp $(, $args)*);
+        $m!(ObservableImp $(, $args)*);
+        $m!(HistoryImp $(, $args)*);
+        #[cfg(feature = "std")]
+        $m!(SyncImp $(, $args)*);
+        #[cfg(feature = "std")]
+        $m!(MutexImp $(, $args)*);
+    };
+}
+
+/*
+    Expands an impl macro for the handle types that can be constructed safely.
+    Used for the impls creating a handle out of nothing but values, which would skip `Imp::new` for the others.
+*/
+macro_rules! for_each_guarded_handle {
+    ($m:ident $(, $args:tt)*) => {
+        $m!(GuardedImp $(, $args)*);
+        #[cfg(feature = "std")]
+        $m!(SyncImp $(, $args)*);
+        #[cfg(feature = "std")]
+        $m!(MutexImp $(, $args)*);
+    };
+}
+
+/*
+    Expands an impl macro for every handle type, with the list of integer and float types as the last argument.
+*/
+macro_rules! for_each_primitive {
+    ($m:ident $(, $args:tt)*) => {
+        for_each_handle!(
+            $m $(, $args)*,
+            [i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64]
+        );
+    };
+}
+
+/*
+    Expands an impl macro for every handle type, with the list of integer types as the last argument.
+*/
+macro_rules! for_each_integer {
+    ($m:ident $(, $args:tt)*) => {
+        for_each_handle!(
+            $m $(, $args)*,
+            [i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize]
+        );
+    };
+}
+
+/*
+    Expands an impl macro for every handle type, once for every pair of distinct integer types as `inner => rhs`.
+    Pairs of the same type are already covered by the impls taking the inner T.
+*/
+macro_rules! for_each_integer_pair {
+    ($m:ident, $tr:ident, $method:ident) => {
+        for_each_integer_pair!(
+            @pairs $m, $tr, $method,
+            i8 => [i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize],
+            i16 => [i8, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize],
+            i32 => [i8, i16, i64, i128, isize, u8, u16, u32, u64, u128, usize],
+            i64 => [i8, i16, i32, i128, isize, u8, u16, u32, u64, u128, usize],
+            i128 => [i8, i16, i32, i64, isize, u8, u16, u32, u64, u128, usize],
+            isize => [i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, usize],
+            u8 => [i8, i16, i32, i64, i128, isize, u16, u32, u64, u128, usize],
+            u16 => [i8, i16, i32, i64, i128, isize, u8, u32, u64, u128, usize],
+            u32 => [i8, i16, i32, i64, i128, isize, u8, u16, u64, u128, usize],
+            u64 => [i8, i16, i32, i64, i128, isize, u8, u16, u32, u128, usize],
+            u128 => [i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, usize],
+            usize => [i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128]
+        );
+    };
+    (@pairs $m:ident, $tr:ident, $method:ident, $($t:ty => [$($u:ty),*]),*) => {
+        $($(
+            for_each_handle!($m, $tr, $method, [$t => $u]);
+        )*)*
+    };
+}
+
+/*
+    Implements a binary operator, with another handle, the inner T or a reference to it as the right-hand side.
+    As the value is shared, owned operands clone it out of the handle, while references are used in place.
+*/
+macro_rules! binary_impl {
+    ($imp:ident, $tr:ident, $method:ident) => {
+        impl<T: $tr + Clone> $tr for crate::$imp<T>
+        where
+            crate::$imp<T::Output>: Wrap<T::Output>,
+        {
+            type Output = crate::$imp<T::Output>;
+
+            #[track_caller]
+            fn $method(self, other: Self) -> Self::Output {
+                let a = self.shared().clone();
+                let b = other.shared().clone();
+                let r = a.$method(b);
+                Wrap::wrap(r)
+            }
+        }
+        impl<T: $tr + Clone> $tr<T> for crate::$imp<T>
+        where
+            crate::$imp<T::Output>: Wrap<T::Output>,
+        {
+            type Output = crate::$imp<T::Output>;
+
+            #[track_caller]
+            fn $method(self, other: T) -> Self::Output {
+                let a = self.shared().clone();
+                let r = a.$method(other);
+                Wrap::wrap(r)
+            }
+        }
+        impl<T, O> $tr<&T> for crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<&'a T, Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self, other: &T) -> Self::Output {
+                let r = (&*self.shared()).$method(other);
+                Wrap::wrap(r)
+            }
+        }
+    };
+}
+
+/*
+    Implements a binary operator on borrowed handles, which neither consumes them nor clones the values.
+    Both sides are read in place through references, taking the locks in the same order as comparisons do.
+*/
+macro_rules! ref_binary_impl {
+    ($imp:ident, $tr:ident, $method:ident) => {
+        impl<T, O> $tr<&crate::$imp<T>> for &crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<&'a T, Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self, other: &crate::$imp<T>) -> Self::Output {
+                let r = self.with_pair(other, |a, b| a.$method(b));
+                Wrap::wrap(r)
+            }
+        }
+        impl<T, O> $tr<crate::$imp<T>> for &crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<&'a T, Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self, other: crate::$imp<T>) -> Self::Output {
+                self.$method(&other)
+            }
+        }
+        impl<T, O> $tr<&crate::$imp<T>> for crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<&'a T, Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self, other: &crate::$imp<T>) -> Self::Output {
+                (&self).$method(other)
+            }
+        }
+        impl<T, O> $tr<T> for &crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<T, Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self, other: T) -> Self::Output {
+                let r = (&*self.shared()).$method(other);
+                Wrap::wrap(r)
+            }
+        }
+        impl<T, O> $tr<&T> for &crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<&'a T, Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self, other: &T) -> Self::Output {
+                let r = (&*self.shared()).$method(other);
+                Wrap::wrap(r)
+            }
+        }
+    };
+}
+
+/*
+    Implements a binary operator with a right-hand side of another type, for each listed `inner => rhs` pair.
+    A blanket `Imp<T> op U` would overlap with `Imp<T> op Imp<T>`, so the pairs have to be listed.
+*/
+macro_rules! mixed_binary_impl {
+    ($imp:ident, $tr:ident, $method:ident, [$($t:ty => $u:ty),*]) => {
+        $(
+            impl $tr<$u> for crate::$imp<$t> {
+                type Output = crate::$imp<<$t as $tr<$u>>::Output>;
+
+                #[track_caller]
+                fn $method(self, other: $u) -> Self::Output {
+                    let a = self.shared().clone();
+                    Wrap::wrap(a.$method(other))
+                }
+            }
+        )*
+    };
+}
+
+/*
+    Implements a binary operator with each listed primitive as the right-hand side, for inner types implementing MixedOps.
+    Requiring MixedOps keeps the impl from overlapping with `Imp<T> op T` when T is the primitive itself.
+*/
+macro_rules! scalar_binary_impl {
+    ($imp:ident, $tr:ident, $method:ident, [$($u:ty),*]) => {
+        $(
+            impl<T: crate::MixedOps + $tr<$u> + Clone> $tr<$u> for crate::$imp<T>
+            where
+                crate::$imp<T::Output>: Wrap<T::Output>,
+            {
+                type Output = crate::$imp<T::Output>;
+
+                #[track_caller]
+                fn $method(self, other: $u) -> Self::Output {
+                    let a = self.shared().clone();
+                    Wrap::wrap(a.$method(other))
+                }
+            }
+        )*
+    };
+}
+
+/*
+    Implements a compound assignment operator, with another handle, the inner T or a reference to either as the right-hand side.
+    A handle on the right-hand side is read before borrowing self, so assigning a clone of the pointer to itself works.
+*/
+macro_rules! assign_impl {
+    ($imp:ident, $tr:ident, $method:ident) => {
+        impl<T: $tr + Clone> $tr for crate::$imp<T> {
+            #[track_caller]
+            fn $method(&mut self, other: crate::$imp<T>) {
+                let b = other.shared().clone();
+                self.exclusive().$method(b);
+            }
+        }
+        impl<T: $tr> $tr<T> for crate::$imp<T> {
+            #[track_caller]
+            fn $method(&mut self, other: T) {
+                self.exclusive().$method(other);
+            }
         }
+        impl<'b, T: $tr<&'b T>> $tr<&'b T> for crate::$imp<T> {
+            #[track_caller]
+            fn $method(&mut self, other: &'b T) {
+                self.exclusive().$method(other);
+            }
+        }
+        impl<T: $tr + Clone> $tr<&crate::$imp<T>> for crate::$imp<T> {
+            #[track_caller]
+            fn $method(&mut self, other: &crate::$imp<T>) {
+                let b = other.shared().clone();
+                self.exclusive().$method(b);
+            }
+        }
+    };
+}
+
+/*
+    Implements a compound assignment operator with a right-hand side of another type, for each listed `inner => rhs` pair.
+*/
+macro_rules! mixed_assign_impl {
+    ($imp:ident, $tr:ident, $method:ident, [$($t:ty => $u:ty),*]) => {
+        $(
+            impl $tr<$u> for crate::$imp<$t> {
+                #[track_caller]
+                fn $method(&mut self, other: $u) {
+                    self.exclusive().$method(other);
+                }
+            }
+        )*
+    };
+}
+
+/*
+    Implements a compound assignment operator with each listed primitive as the right-hand side, for inner types implementing MixedOps.
+*/
+macro_rules! scalar_assign_impl {
+    ($imp:ident, $tr:ident, $method:ident, [$($u:ty),*]) => {
+        $(
+            impl<T: crate::MixedOps + $tr<$u>> $tr<$u> for crate::$imp<T> {
+                #[track_caller]
+                fn $method(&mut self, other: $u) {
+                    self.exclusive().$method(other);
+                }
+            }
+        )*
+    };
+}
+
+/*
+    Implements a binary operator with a primitive on the left-hand side and a handle to one on the right.
+    Coherence only allows this for concrete types, so it is implemented for the listed primitives.
+*/
+macro_rules! prim_binary_impl {
+    ($imp:ident, $tr:ident, $method:ident, [$($t:ty),*]) => {
+        $(
+            impl $tr<crate::$imp<$t>> for $t {
+                type Output = crate::$imp<<$t as $tr>::Output>;
+
+                #[track_caller]
+                fn $method(self, other: crate::$imp<$t>) -> Self::Output {
+                    let b = *other.shared();
+                    Wrap::wrap(self.$method(b))
+                }
+            }
+        )*
+    };
+}
+
+/*
+    Implements a compound assignment operator on a primitive, with a handle to one on the right-hand side.
+*/
+macro_rules! prim_assign_impl {
+    ($imp:ident, $tr:ident, $method:ident, [$($t:ty),*]) => {
+        $(
+            impl $tr<crate::$imp<$t>> for $t {
+                #[track_caller]
+                fn $method(&mut self, other: crate::$imp<$t>) {
+                    self.$method(*other.shared());
+                }
+            }
+        )*
+    };
+}
+
+/*
+    Implements a unary operator.
+*/
+macro_rules! unary_impl {
+    ($imp:ident, $tr:ident, $method:ident) => {
+        impl<T: $tr + Clone> $tr for crate::$imp<T>
+        where
+            crate::$imp<T::Output>: Wrap<T::Output>,
+        {
+            type Output = crate::$imp<T::Output>;
+
+            #[track_caller]
+            fn $method(self) -> Self::Output {
+                Wrap::wrap(self.shared().clone().$method())
+            }
+        }
+        impl<T, O> $tr for &crate::$imp<T>
+        where
+            for<'a> &'a T: $tr<Output = O>,
+            crate::$imp<O>: Wrap<O>,
+        {
+            type Output = crate::$imp<O>;
+
+            #[track_caller]
+            fn $method(self) -> Self::Output {
+                Wrap::wrap((&*self.shared()).$method())
+            }
+        }
+    };
+}
+
+/*
+    Allows using != and ==.
+*/
+mod eq_partial_eq_impl {
+    use super::Handle;
+
+    macro_rules! eq_impl {
+        ($imp:ident) => {
+            impl<T: PartialEq> PartialEq for crate::$imp<T> {
+                fn eq(&self, other: &Self) -> bool {
+                    self.with_pair(other, |a, b| a == b)
+                }
+            }
+
+            impl<T: PartialEq> PartialEq<T> for crate::$imp<T> {
+                fn eq(&self, other: &T) -> bool {
+                    let a = self.shared();
+                    a.eq(other)
+                }
+            }
+            impl<T: Eq> Eq for crate::$imp<T> {}
+        };
     }
-    impl<T: Eq> Eq for Imp<T> {}
+    for_each_handle!(eq_impl);
+
+    /*
+        Allows comparing with the inner value on the left hand side.
+        Coherence only allows this for concrete types, so it is implemented for the primitives and String.
+    */
+    macro_rules! eq_rev_impl {
+        ($imp:ident, [$($t:ty),*]) => {
+            $(
+                impl PartialEq<crate::$imp<$t>> for $t {
+                    fn eq(&self, other: &crate::$imp<$t>) -> bool {
+                        *self == *other.shared()
+                    }
+                }
+            )*
+        };
+    }
+    for_each_handle!(
+        eq_rev_impl,
+        [
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            isize,
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            usize,
+            f32,
+            f64,
+            bool,
+            char,
+            alloc::string::String
+        ]
+    );
+}
+
+/*
+    Allows hashing the pointer by its inner value, consistent with PartialEq.
+*/
+mod hash_impl {
+    use core::hash::{Hash, Hasher};
+
+    use super::Handle;
+
+    macro_rules! hash_impl {
+        ($imp:ident) => {
+            impl<T: ?Sized + Hash> Hash for crate::$imp<T> {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.shared().hash(state)
+                }
+            }
+        };
+    }
+    for_each_handle!(hash_impl);
 }
 
 /*
     Allows using < > >= <=
 */
 mod partialord_ord_impl {
-    use crate::Imp;
-    use std::ops::Deref;
-
-    impl<T: PartialOrd> PartialOrd for Imp<T> {
-        fn partial_cmp(&self, other: &Self) -> std::option::Option<std::cmp::Ordering> {
-            let a = self.deref();
-            let b = other.deref();
-            if a < b {
-                Some(std::cmp::Ordering::Less)
-            } else if a > b {
-                Some(std::cmp::Ordering::Greater)
-            } else {
-                Some(std::cmp::Ordering::Equal)
+    use core::cmp::Ordering;
+
+    use super::Handle;
+
+    macro_rules! ord_impl {
+        ($imp:ident) => {
+            impl<T: PartialOrd> PartialOrd for crate::$imp<T> {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    self.with_pair(other, |a, b| a.partial_cmp(b))
+                }
             }
-        }
-    }
-    impl<T: PartialOrd> PartialOrd<T> for Imp<T> {
-        fn partial_cmp(&self, other: &T) -> std::option::Option<std::cmp::Ordering> {
-            let a = self.deref();
-            let b = other;
-            if a < b {
-                Some(std::cmp::Ordering::Less)
-            } else if a > b {
-                Some(std::cmp::Ordering::Greater)
-            } else {
-                Some(std::cmp::Ordering::Equal)
+            impl<T: PartialOrd> PartialOrd<T> for crate::$imp<T> {
+                fn partial_cmp(&self, other: &T) -> Option<Ordering> {
+                    self.shared().partial_cmp(other)
+                }
             }
-        }
+            impl<T: Ord> Ord for crate::$imp<T> {
+                fn cmp(&self, other: &Self) -> Ordering {
+                    self.with_pair(other, |a, b| a.cmp(b))
+                }
+            }
+        };
     }
-    impl<T: Ord> Ord for Imp<T> {
-        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-            self.partial_cmp(other).unwrap()
-        }
+    for_each_handle!(ord_impl);
+
+    macro_rules! ord_rev_impl {
+        ($imp:ident, [$($t:ty),*]) => {
+            $(
+                impl PartialOrd<crate::$imp<$t>> for $t {
+                    fn partial_cmp(&self, other: &crate::$imp<$t>) -> Option<Ordering> {
+                        self.partial_cmp(&*other.shared())
+                    }
+                }
+            )*
+        };
     }
+    for_each_handle!(
+        ord_rev_impl,
+        [
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            isize,
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            usize,
+            f32,
+            f64,
+            bool,
+            char,
+            alloc::string::String
+        ]
+    );
 }
 /*
     Allows using the Debug and Display implementation of the inner T.
 */
 mod debug_display_impl {
-    use std::fmt::Debug;
-    use std::fmt::Display;
+    use core::fmt::Debug;
+    use core::fmt::Display;
 
-    use crate::Imp;
+    use super::Handle;
 
-    impl<T: Debug> Debug for Imp<T> {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            self.v.borrow().fmt(f)
-        }
+    macro_rules! debug_display_impl {
+        ($imp:ident) => {
+            impl<T: Debug> Debug for crate::$imp<T> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    self.shared().fmt(f)
+                }
+            }
+
+            impl<T: Display> Display for crate::$imp<T> {
+                fn fmt(
+                    &self,
+                    f: &mut core::fmt::Formatter<'_>,
+                ) -> core::result::Result<(), core::fmt::Error> {
+                    self.shared().fmt(f)
+                }
+            }
+        };
     }
+    for_each_handle!(debug_display_impl);
+}
 
-    impl<T: Display> Display for Imp<T> {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-            self.v.borrow().fmt(f)
-        }
+/*
+    Allows summing and multiplying iterators of handles, into a handle or into a primitive.
+*/
+mod sum_product_impl {
+    use super::{Handle, Wrap};
+    use core::iter::{Product, Sum};
+
+    macro_rules! sum_product_impl {
+        ($imp:ident, $tr:ident, $method:ident) => {
+            impl<T: $tr + Clone> $tr for crate::$imp<T>
+            where
+                Self: Wrap<T>,
+            {
+                #[track_caller]
+                fn $method<I: Iterator<Item = Self>>(iter: I) -> Self {
+                    Wrap::wrap(iter.map(|p| p.shared().clone()).$method())
+                }
+            }
+            impl<'a, T: $tr + Clone + 'a> $tr<&'a crate::$imp<T>> for crate::$imp<T>
+            where
+                Self: Wrap<T>,
+            {
+                #[track_caller]
+                fn $method<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                    Wrap::wrap(iter.map(|p| p.shared().clone()).$method())
+                }
+            }
+        };
+    }
+    // Summing an empty iterator creates a handle, so only for the ones that can be constructed safely.
+    for_each_guarded_handle!(sum_product_impl, Sum, sum);
+    for_each_guarded_handle!(sum_product_impl, Product, product);
+
+    macro_rules! prim_sum_product_impl {
+        ($imp:ident, $tr:ident, $method:ident, [$($t:ty),*]) => {
+            $(
+                impl $tr<crate::$imp<$t>> for $t {
+                    #[track_caller]
+                    fn $method<I: Iterator<Item = crate::$imp<$t>>>(iter: I) -> Self {
+                        iter.map(|p| *p.shared()).$method()
+                    }
+                }
+                impl<'a> $tr<&'a crate::$imp<$t>> for $t {
+                    #[track_caller]
+                    fn $method<I: Iterator<Item = &'a crate::$imp<$t>>>(iter: I) -> Self {
+                        iter.map(|p| *p.shared()).$method()
+                    }
+                }
+            )*
+        };
     }
+    for_each_primitive!(prim_sum_product_impl, Sum, sum);
+    for_each_primitive!(prim_sum_product_impl, Product, product);
 }
 
 /*
-    Allows indexing with range bounds.
+    Allows extending and collecting into handles to collections.
+*/
+mod extend_from_iter_impl {
+    use super::{Handle, Wrap};
+
+    macro_rules! extend_from_iter_impl {
+        ($imp:ident) => {
+            impl<A, C: Extend<A>> Extend<A> for crate::$imp<C> {
+                #[track_caller]
+                fn extend<I: IntoIterator<Item = A>>(&mut self, iter: I) {
+                    self.exclusive().extend(iter)
+                }
+            }
+        };
+    }
+    for_each_handle!(extend_from_iter_impl);
+
+    macro_rules! from_iter_impl {
+        ($imp:ident) => {
+            impl<A, C: FromIterator<A>> FromIterator<A> for crate::$imp<C>
+            where
+                Self: Wrap<C>,
+            {
+                fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+                    Wrap::wrap(C::from_iter(iter))
+                }
+            }
+        };
+    }
+    for_each_guarded_handle!(from_iter_impl);
+}
+
+/*
+    Allows indexing with anything the inner T can be indexed with, like keys and all kinds of ranges.
+    Only implemented for the pointers with unchecked access, as the returned reference can not outlive a guard.
 */
 mod index_indexmut_impl {
-    use std::ops::{Deref, DerefMut, Index, IndexMut, Range};
+    use core::ops::{Deref, DerefMut, Index, IndexMut};
+
+    macro_rules! index_indexmut_impl {
+        ($imp:ident) => {
+            impl<T: ?Sized + Index<I>, I> Index<I> for crate::$imp<T> {
+                type Output = T::Output;
+
+                #[track_caller]
+                fn index(&self, index: I) -> &Self::Output {
+                    let p = self.deref();
+                    &p[index]
+                }
+            }
+            impl<T: ?Sized + IndexMut<I>, I> IndexMut<I> for crate::$imp<T> {
+                #[track_caller]
+                fn index_mut(&mut self, index: I) -> &mut Self::Output {
+                    let p = self.deref_mut();
+                    p.index_mut(index)
+                }
+            }
+        };
+    }
+    index_indexmut_impl!(Imp);
+    index_indexmut_impl!(ObservableImp);
+    index_indexmut_impl!(HistoryImp);
+}
+
+/*
+    Allows iterating over the collection behind an Imp.
+    Only implemented for Imp, as the borrowing iterators can not outlive a guard.
+*/
+mod into_iter_impl {
+    use core::ops::{Deref, DerefMut};
 
     use crate::Imp;
 
-    impl<T: Index<usize>> Index<usize> for Imp<T> {
-        type Output = T::Output;
+    impl<'a, C: ?Sized> IntoIterator for &'a Imp<C>
+    where
+        &'a C: IntoIterator,
+    {
+        type Item = <&'a C as IntoIterator>::Item;
+        type IntoIter = <&'a C as IntoIterator>::IntoIter;
 
-        fn index(&self, index: usize) -> &Self::Output {
-            let p = self.deref();
-            &p[index]
+        fn into_iter(self) -> Self::IntoIter {
+            self.deref().into_iter()
         }
     }
-    impl<T: Index<Range<usize>>> Index<Range<usize>> for Imp<T> {
-        type Output = T::Output;
 
-        fn index(&self, index: Range<usize>) -> &Self::Output {
-            let p = self.deref();
-            &p[index]
-        }
-    }
-    impl<T: IndexMut<usize>> IndexMut<usize> for Imp<T> {
-        fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-            let p = self.deref_mut();
-            p.index_mut(index)
+    impl<'a, C: ?Sized> IntoIterator for &'a mut Imp<C>
+    where
+        &'a mut C: IntoIterator,
+    {
+        type Item = <&'a mut C as IntoIterator>::Item;
+        type IntoIter = <&'a mut C as IntoIterator>::IntoIter;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.deref_mut().into_iter()
         }
     }
-    impl<T: IndexMut<Range<usize>>> IndexMut<Range<usize>> for Imp<T> {
-        fn index_mut(&mut self, index: Range<usize>) -> &mut Self::Output {
-            let p = self.deref_mut();
-            p.index_mut(index)
+
+    /*
+        Takes the collection out if this is the only pointer to it, and iterates over a clone otherwise.
+    */
+    impl<C: IntoIterator + Clone> IntoIterator for Imp<C> {
+        type Item = C::Item;
+        type IntoIter = C::IntoIter;
+
+        fn into_iter(self) -> Self::IntoIter {
+            match Imp::try_unwrap(self) {
+                Ok(c) => c.into_iter(),
+                Err(p) => Imp::read(&p).clone().into_iter(),
+            }
         }
     }
 }
@@ -125,511 +980,326 @@ mod index_indexmut_impl {
     Allows the use of the + operator
 */
 mod add_impl {
-    use crate::Imp;
-    use std::ops::{Add, Deref};
-
-    impl<T: Add<T> + Copy + Add<Output = T>> Add for Imp<T> {
-        type Output = Imp<T>;
-
-        fn add(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.add(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Add<T> + Copy + Add<Output = T>> Add<T> for Imp<T> {
-        type Output = Imp<T>;
-
-        fn add(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.add(b);
-            unsafe { Imp::new(r) }
-        }
+    use super::{Handle, Wrap};
+    use alloc::string::String;
+    use core::ops::Add;
+    #[cfg(feature = "std")]
+    use std::time::{Duration, Instant, SystemTime};
+
+    for_each_handle!(binary_impl, Add, add);
+    for_each_handle!(ref_binary_impl, Add, add);
+    for_each_primitive!(scalar_binary_impl, Add, add);
+    for_each_primitive!(prim_binary_impl, Add, add);
+    #[cfg(feature = "std")]
+    for_each_handle!(mixed_binary_impl, Add, add, [Instant => Duration, SystemTime => Duration]);
+
+    /*
+        Allows concatenating a string slice, the same way as String does.
+    */
+    macro_rules! string_add_impl {
+        ($imp:ident) => {
+            impl Add<&str> for crate::$imp<String> {
+                type Output = crate::$imp<String>;
+
+                #[track_caller]
+                fn add(self, other: &str) -> Self::Output {
+                    let a = self.shared().clone();
+                    Wrap::wrap(a + other)
+                }
+            }
+        };
     }
+    for_each_handle!(string_add_impl);
 }
+
 /*
     Allows the use of the += operator
 */
 mod add_assign_impl {
-    use crate::Imp;
-    use std::ops::AddAssign;
-
-    impl<T: AddAssign<T> + Copy + AddAssign> AddAssign for Imp<T> {
-        fn add_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().add_assign(*other.v.borrow());
-        }
-    }
-    impl<T: AddAssign<T> + Copy + AddAssign> AddAssign<T> for Imp<T> {
-        fn add_assign(&mut self, other: T) {
-            self.v.borrow_mut().add_assign(other);
-        }
+    use super::Handle;
+    use alloc::string::String;
+    use core::ops::AddAssign;
+    #[cfg(feature = "std")]
+    use std::time::{Duration, Instant, SystemTime};
+
+    for_each_handle!(assign_impl, AddAssign, add_assign);
+    for_each_primitive!(scalar_assign_impl, AddAssign, add_assign);
+    for_each_primitive!(prim_assign_impl, AddAssign, add_assign);
+    #[cfg(feature = "std")]
+    for_each_handle!(mixed_assign_impl, AddAssign, add_assign, [Instant => Duration, SystemTime => Duration]);
+
+    macro_rules! string_add_assign_impl {
+        ($imp:ident) => {
+            impl AddAssign<&str> for crate::$imp<String> {
+                #[track_caller]
+                fn add_assign(&mut self, other: &str) {
+                    self.exclusive().push_str(other);
+                }
+            }
+        };
     }
+    for_each_handle!(string_add_assign_impl);
 }
 
 /*
     Allows the use of the & operator
 */
 mod bitand_impl {
-    use crate::Imp;
-    use std::ops::{BitAnd, Deref};
+    use super::{Handle, Wrap};
+    use core::ops::BitAnd;
 
-    impl<T: BitAnd<T> + Copy + BitAnd<Output = T>> BitAnd for Imp<T> {
-        type Output = Self;
-
-        fn bitand(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.bitand(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: BitAnd<T> + Copy + BitAnd<Output = T>> BitAnd<T> for Imp<T> {
-        type Output = Self;
-
-        fn bitand(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.bitand(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    for_each_handle!(binary_impl, BitAnd, bitand);
+    for_each_handle!(ref_binary_impl, BitAnd, bitand);
+    for_each_primitive!(scalar_binary_impl, BitAnd, bitand);
+    for_each_integer!(prim_binary_impl, BitAnd, bitand);
 }
 
 /*
-    Allows the use of the &= operator.
-    BitAndAssign    The bitwise AND assignment operator &=.
+    Allows the use of the &= operator
 */
 mod bitand_assign_impl {
-    use crate::Imp;
-    use std::ops::BitAndAssign;
+    use super::Handle;
+    use core::ops::BitAndAssign;
 
-    impl<T: BitAndAssign<T> + Copy + BitAndAssign> BitAndAssign for Imp<T> {
-        fn bitand_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().bitand_assign(*other.v.borrow());
-        }
-    }
-    impl<T: BitAndAssign<T> + Copy + BitAndAssign> BitAndAssign<T> for Imp<T> {
-        fn bitand_assign(&mut self, other: T) {
-            self.v.borrow_mut().bitand_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, BitAndAssign, bitand_assign);
+    for_each_primitive!(scalar_assign_impl, BitAndAssign, bitand_assign);
+    for_each_integer!(prim_assign_impl, BitAndAssign, bitand_assign);
 }
 
 /*
     Allows the use of the | operator
 */
 mod bitor_impl {
-    use crate::Imp;
-    use std::ops::{BitOr, Deref};
+    use super::{Handle, Wrap};
+    use core::ops::BitOr;
 
-    impl<T: BitOr<T> + Copy + BitOr<Output = T>> BitOr for Imp<T> {
-        type Output = Self;
-
-        fn bitor(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.bitor(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: BitOr<T> + Copy + BitOr<Output = T>> BitOr<T> for Imp<T> {
-        type Output = Self;
-
-        fn bitor(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.bitor(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    for_each_handle!(binary_impl, BitOr, bitor);
+    for_each_handle!(ref_binary_impl, BitOr, bitor);
+    for_each_primitive!(scalar_binary_impl, BitOr, bitor);
+    for_each_integer!(prim_binary_impl, BitOr, bitor);
 }
 
 /*
     Allows the use of the |= operator
 */
 mod bitor_assign_impl {
-    use crate::Imp;
-    use std::ops::BitOrAssign;
+    use super::Handle;
+    use core::ops::BitOrAssign;
 
-    impl<T: BitOrAssign<T> + Copy + BitOrAssign> BitOrAssign for Imp<T> {
-        fn bitor_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().bitor_assign(*other.v.borrow());
-        }
-    }
-    impl<T: BitOrAssign<T> + Copy + BitOrAssign> BitOrAssign<T> for Imp<T> {
-        fn bitor_assign(&mut self, other: T) {
-            self.v.borrow_mut().bitor_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, BitOrAssign, bitor_assign);
+    for_each_primitive!(scalar_assign_impl, BitOrAssign, bitor_assign);
+    for_each_integer!(prim_assign_impl, BitOrAssign, bitor_assign);
 }
 
 /*
     Allows the use of the ^ operator
 */
 mod bitxor_impl {
-    use crate::Imp;
-    use std::ops::{BitXor, Deref};
+    use super::{Handle, Wrap};
+    use core::ops::BitXor;
 
-    impl<T: BitXor<T> + Copy + BitXor<Output = T>> BitXor for Imp<T> {
-        type Output = Self;
-
-        fn bitxor(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.bitxor(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: BitXor<T> + Copy + BitXor<Output = T>> BitXor<T> for Imp<T> {
-        type Output = Self;
-
-        fn bitxor(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.bitxor(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    for_each_handle!(binary_impl, BitXor, bitxor);
+    for_each_handle!(ref_binary_impl, BitXor, bitxor);
+    for_each_primitive!(scalar_binary_impl, BitXor, bitxor);
+    for_each_integer!(prim_binary_impl, BitXor, bitxor);
 }
 
 /*
     Allows the use of the ^= operator
 */
 mod bitxor_assign_impl {
-    use crate::Imp;
-    use std::ops::BitXorAssign;
+    use super::Handle;
+    use core::ops::BitXorAssign;
 
-    impl<T: BitXorAssign<T> + Copy + BitXorAssign> BitXorAssign for Imp<T> {
-        fn bitxor_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().bitxor_assign(*other.v.borrow());
-        }
-    }
-    impl<T: BitXorAssign<T> + Copy + BitXorAssign> BitXorAssign<T> for Imp<T> {
-        fn bitxor_assign(&mut self, other: T) {
-            self.v.borrow_mut().bitxor_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, BitXorAssign, bitxor_assign);
+    for_each_primitive!(scalar_assign_impl, BitXorAssign, bitxor_assign);
+    for_each_integer!(prim_assign_impl, BitXorAssign, bitxor_assign);
 }
 
 /*
     Allows the use of the ! operator
 */
 mod not_impl {
-    use crate::Imp;
-    use std::ops::Not;
-
-    impl<T: Not + Copy> Not for Imp<T> {
-        type Output = Imp<T::Output>;
+    use super::{Handle, Wrap};
+    use core::ops::Not;
 
-        fn not(self) -> Self::Output {
-            unsafe { Imp::new(self.v.borrow().not()) }
-        }
-    }
+    for_each_handle!(unary_impl, Not, not);
 }
 
 /*
     Allows the use of the / operator
 */
 mod div_impl {
-    use crate::Imp;
-    use std::ops::{Deref, Div};
-
-    impl<T: Div<T> + Copy + Div<Output = T>> Div for Imp<T> {
-        type Output = Self;
-
-        fn div(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.div(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Div<T> + Copy + Div<Output = T>> Div<T> for Imp<T> {
-        type Output = Self;
-
-        fn div(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.div(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    use super::{Handle, Wrap};
+    use core::{ops::Div, time::Duration};
+
+    for_each_handle!(binary_impl, Div, div);
+    for_each_handle!(ref_binary_impl, Div, div);
+    for_each_primitive!(scalar_binary_impl, Div, div);
+    for_each_primitive!(prim_binary_impl, Div, div);
+    for_each_handle!(mixed_binary_impl, Div, div, [Duration => u32]);
 }
 
 /*
     Allows the use of the /= operator
 */
 mod div_assign_impl {
-    use crate::Imp;
-    use std::ops::DivAssign;
+    use super::Handle;
+    use core::{ops::DivAssign, time::Duration};
 
-    impl<T: DivAssign<T> + Copy + DivAssign> DivAssign for Imp<T> {
-        fn div_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().div_assign(*other.v.borrow());
-        }
-    }
-    impl<T: DivAssign<T> + Copy + DivAssign> DivAssign<T> for Imp<T> {
-        fn div_assign(&mut self, other: T) {
-            self.v.borrow_mut().div_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, DivAssign, div_assign);
+    for_each_primitive!(scalar_assign_impl, DivAssign, div_assign);
+    for_each_primitive!(prim_assign_impl, DivAssign, div_assign);
+    for_each_handle!(mixed_assign_impl, DivAssign, div_assign, [Duration => u32]);
 }
 
 /*
     Allows the use of the * operator
 */
 mod mul_impl {
-    use crate::Imp;
-    use std::ops::{Deref, Mul};
-
-    impl<T: Mul<T> + Copy + Mul<Output = T>> Mul for Imp<T> {
-        type Output = Self;
-
-        fn mul(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.mul(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Mul<T> + Copy + Mul<Output = T>> Mul<T> for Imp<T> {
-        type Output = Self;
-
-        fn mul(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.mul(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    use super::{Handle, Wrap};
+    use core::{ops::Mul, time::Duration};
+
+    for_each_handle!(binary_impl, Mul, mul);
+    for_each_handle!(ref_binary_impl, Mul, mul);
+    for_each_primitive!(scalar_binary_impl, Mul, mul);
+    for_each_primitive!(prim_binary_impl, Mul, mul);
+    for_each_handle!(mixed_binary_impl, Mul, mul, [Duration => u32]);
 }
 
 /*
     Allows the use of the *= operator
 */
 mod mul_assign_impl {
-    use crate::Imp;
-    use std::ops::MulAssign;
+    use super::Handle;
+    use core::{ops::MulAssign, time::Duration};
 
-    impl<T: MulAssign<T> + Copy + MulAssign> MulAssign for Imp<T> {
-        fn mul_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().mul_assign(*other.v.borrow());
-        }
-    }
-    impl<T: MulAssign<T> + Copy + MulAssign> MulAssign<T> for Imp<T> {
-        fn mul_assign(&mut self, other: T) {
-            self.v.borrow_mut().mul_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, MulAssign, mul_assign);
+    for_each_primitive!(scalar_assign_impl, MulAssign, mul_assign);
+    for_each_primitive!(prim_assign_impl, MulAssign, mul_assign);
+    for_each_handle!(mixed_assign_impl, MulAssign, mul_assign, [Duration => u32]);
 }
 
 /*
-    Allows the use of the ! operator
+    Allows the use of the unary - operator
 */
 mod neg_impl {
-    use crate::Imp;
-    use std::ops::Neg;
-
-    impl<T: Neg + Copy> Neg for Imp<T> {
-        type Output = Imp<T::Output>;
+    use super::{Handle, Wrap};
+    use core::ops::Neg;
 
-        fn neg(self) -> Self::Output {
-            unsafe { Imp::new(self.v.borrow().neg()) }
-        }
-    }
+    for_each_handle!(unary_impl, Neg, neg);
 }
 
 /*
     Allows the use of the % operator
 */
 mod rem_impl {
-    use crate::Imp;
-    use std::ops::{Deref, Rem};
+    use super::{Handle, Wrap};
+    use core::ops::Rem;
 
-    impl<T: Rem<T> + Copy + Rem<Output = T>> Rem for Imp<T> {
-        type Output = Self;
-
-        fn rem(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.rem(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Rem<T> + Copy + Rem<Output = T>> Rem<T> for Imp<T> {
-        type Output = Self;
-
-        fn rem(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.rem(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    for_each_handle!(binary_impl, Rem, rem);
+    for_each_handle!(ref_binary_impl, Rem, rem);
+    for_each_primitive!(scalar_binary_impl, Rem, rem);
+    for_each_primitive!(prim_binary_impl, Rem, rem);
 }
 
 /*
     Allows the use of the %= operator
 */
 mod rem_assign_impl {
-    use crate::Imp;
-    use std::ops::RemAssign;
+    use super::Handle;
+    use core::ops::RemAssign;
 
-    impl<T: RemAssign<T> + Copy + RemAssign> RemAssign for Imp<T> {
-        fn rem_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().rem_assign(*other.v.borrow());
-        }
-    }
-    impl<T: RemAssign<T> + Copy + RemAssign> RemAssign<T> for Imp<T> {
-        fn rem_assign(&mut self, other: T) {
-            self.v.borrow_mut().rem_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, RemAssign, rem_assign);
+    for_each_primitive!(scalar_assign_impl, RemAssign, rem_assign);
+    for_each_primitive!(prim_assign_impl, RemAssign, rem_assign);
 }
 
 /*
     Allows the use of the << operator
 */
 mod shl_impl {
-    use crate::Imp;
-    use std::ops::{Deref, Shl};
-
-    impl<T: Shl<T> + Copy + Shl<Output = T>> Shl for Imp<T> {
-        type Output = Self;
-
-        fn shl(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.shl(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Shl<T> + Copy + Shl<Output = T>> Shl<T> for Imp<T> {
-        type Output = Self;
-
-        fn shl(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.shl(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    use super::{Handle, Wrap};
+    use core::ops::Shl;
+
+    for_each_handle!(binary_impl, Shl, shl);
+    for_each_handle!(ref_binary_impl, Shl, shl);
+    for_each_primitive!(scalar_binary_impl, Shl, shl);
+    for_each_integer!(prim_binary_impl, Shl, shl);
+    for_each_integer_pair!(mixed_binary_impl, Shl, shl);
 }
 
 /*
     Allows the use of the <<= operator
 */
 mod shl_assign_impl {
-    use crate::Imp;
-    use std::ops::ShlAssign;
+    use super::Handle;
+    use core::ops::ShlAssign;
 
-    impl<T: ShlAssign<T> + Copy + ShlAssign> ShlAssign for Imp<T> {
-        fn shl_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().shl_assign(*other.v.borrow());
-        }
-    }
-    impl<T: ShlAssign<T> + Copy + ShlAssign> ShlAssign<T> for Imp<T> {
-        fn shl_assign(&mut self, other: T) {
-            self.v.borrow_mut().shl_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, ShlAssign, shl_assign);
+    for_each_primitive!(scalar_assign_impl, ShlAssign, shl_assign);
+    for_each_integer!(prim_assign_impl, ShlAssign, shl_assign);
+    for_each_integer_pair!(mixed_assign_impl, ShlAssign, shl_assign);
 }
 
 /*
     Allows the use of the >> operator
 */
 mod shr_impl {
-    use crate::Imp;
-    use std::ops::{Deref, Shr};
-
-    impl<T: Shr<T> + Copy + Shr<Output = T>> Shr for Imp<T> {
-        type Output = Self;
-
-        fn shr(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.shr(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Shr<T> + Copy + Shr<Output = T>> Shr<T> for Imp<T> {
-        type Output = Self;
-
-        fn shr(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.shr(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    use super::{Handle, Wrap};
+    use core::ops::Shr;
+
+    for_each_handle!(binary_impl, Shr, shr);
+    for_each_handle!(ref_binary_impl, Shr, shr);
+    for_each_primitive!(scalar_binary_impl, Shr, shr);
+    for_each_integer!(prim_binary_impl, Shr, shr);
+    for_each_integer_pair!(mixed_binary_impl, Shr, shr);
 }
 
 /*
     Allows the use of the >>= operator
 */
 mod shr_assign_impl {
-    use crate::Imp;
-    use std::ops::ShrAssign;
+    use super::Handle;
+    use core::ops::ShrAssign;
 
-    impl<T: ShrAssign<T> + Copy + ShrAssign> ShrAssign for Imp<T> {
-        fn shr_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().shr_assign(*other.v.borrow());
-        }
-    }
-    impl<T: ShrAssign<T> + Copy + ShrAssign> ShrAssign<T> for Imp<T> {
-        fn shr_assign(&mut self, other: T) {
-            self.v.borrow_mut().shr_assign(other);
-        }
-    }
+    for_each_handle!(assign_impl, ShrAssign, shr_assign);
+    for_each_primitive!(scalar_assign_impl, ShrAssign, shr_assign);
+    for_each_integer!(prim_assign_impl, ShrAssign, shr_assign);
+    for_each_integer_pair!(mixed_assign_impl, ShrAssign, shr_assign);
 }
 
 /*
     Allows the use of the - operator
 */
 mod sub_impl {
-    use crate::Imp;
-    use std::ops::{Deref, Sub};
-
-    impl<T: Sub<T> + Copy + Sub<Output = T>> Sub for Imp<T> {
-        type Output = Imp<T>;
-
-        fn sub(self, other: Self) -> Self::Output {
-            let a = *self.deref();
-            let b = *other.deref();
-            let r = a.sub(b);
-            unsafe { Imp::new(r) }
-        }
-    }
-    impl<T: Sub<T> + Copy + Sub<Output = T>> Sub<T> for Imp<T> {
-        type Output = Imp<T>;
-
-        fn sub(self, other: T) -> Self::Output {
-            let a = *self.deref();
-            let b = other;
-            let r = a.sub(b);
-            unsafe { Imp::new(r) }
-        }
-    }
+    use super::{Handle, Wrap};
+    use core::ops::Sub;
+    #[cfg(feature = "std")]
+    use std::time::{Duration, Instant, SystemTime};
+
+    for_each_handle!(binary_impl, Sub, sub);
+    for_each_handle!(ref_binary_impl, Sub, sub);
+    for_each_primitive!(scalar_binary_impl, Sub, sub);
+    for_each_primitive!(prim_binary_impl, Sub, sub);
+    #[cfg(feature = "std")]
+    for_each_handle!(mixed_binary_impl, Sub, sub, [Instant => Duration, SystemTime => Duration]);
 }
+
 /*
     Allows the use of the -= operator
 */
 mod sub_assign_impl {
-    use crate::Imp;
-    use std::ops::SubAssign;
-
-    impl<T: SubAssign<T> + Copy + SubAssign> SubAssign for Imp<T> {
-        fn sub_assign(&mut self, other: Imp<T>) {
-            self.v.borrow_mut().sub_assign(*other.v.borrow());
-        }
-    }
-    impl<T: SubAssign<T> + Copy + SubAssign> SubAssign<T> for Imp<T> {
-        fn sub_assign(&mut self, other: T) {
-            self.v.borrow_mut().sub_assign(other);
-        }
-    }
+    use super::Handle;
+    use core::ops::SubAssign;
+    #[cfg(feature = "std")]
+    use std::time::{Duration, Instant, SystemTime};
+
+    for_each_handle!(assign_impl, SubAssign, sub_assign);
+    for_each_primitive!(scalar_assign_impl, SubAssign, sub_assign);
+    for_each_primitive!(prim_assign_impl, SubAssign, sub_assign);
+    #[cfg(feature = "std")]
+    for_each_handle!(mixed_assign_impl, SubAssign, sub_assign, [Instant => Duration, SystemTime => Duration]);
 }
 
 // TODO do we need to implenent Drop?
@@ -1,11 +1,51 @@
-#![feature(dispatch_from_dyn)]
-#![feature(unsize)]
-#![feature(coerce_unsized)]
+#![cfg_attr(feature = "nightly", feature(dispatch_from_dyn))]
+#![cfg_attr(feature = "nightly", feature(unsize))]
+#![cfg_attr(feature = "nightly", feature(coerce_unsized))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+mod any;
+mod by_ptr;
+#[cfg(feature = "checked")]
+mod checked;
+#[cfg(feature = "std")]
+mod computed;
+mod guard;
+mod history;
 mod imp_impls;
+mod mapped;
+mod observable;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod slice;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(test)]
 mod tests;
+mod transaction;
+mod weak;
+
+pub use by_ptr::ByPtr;
+#[cfg(feature = "std")]
+pub use computed::Computed;
+pub use guard::{GuardedImp, ImpRef, ImpRefMut};
+pub use history::HistoryImp;
+pub use imp_impls::MixedOps;
+pub use mapped::MappedImp;
+pub use observable::{ObservableImp, ObservableRefMut, Subscription};
+#[cfg(all(feature = "serde", feature = "std"))]
+pub use serde_impl::{deserialize_unchecked, identity};
+#[cfg(feature = "std")]
+pub use sync::{LockError, MutexImp, SyncImp};
+pub use transaction::Transaction;
+pub use weak::WeakImp;
 
-use std::{cell::RefCell, rc::Rc};
+use alloc::rc::Rc;
+use core::{
+    cell::{BorrowError, BorrowMutError, RefCell},
+    mem::MaybeUninit,
+};
 
 #[doc = include_str!("../readme.md")]
 pub struct Imp<T: ?Sized> {
@@ -32,12 +72,66 @@ impl<T> Imp<T> {
     /// # Safety
     /// `DerefMut` implementation is unsound due to this library essentially working around the runtime safety provided
     /// by using `RefCell`. See [Issue #2](https://github.com/samhamnam/interior_mutability_pointer/issues/2).
+    /// Use [`GuardedImp`] for a pointer that can be constructed safely.
     pub unsafe fn new(t: T) -> Self {
         Self {
             v: Rc::new(RefCell::new(t)),
         }
     }
 
+    /// Returns a pointer to a value built by `f`, which is given a [`WeakImp`] to the value being built.
+    ///
+    /// Allows self-referential values, like a node holding a weak pointer to itself,
+    /// without wrapping the field in an `Option` to fill it in afterwards.
+    /// Upgrading the weak pointer inside `f` returns `None`, as the value does not exist yet.
+    ///
+    /// # Arguments
+    /// * `f` - Builds the value, given a weak pointer to it
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::{Imp, WeakImp};
+    /// struct Node {
+    ///     me: WeakImp<Node>,
+    ///     value: i32,
+    /// }
+    /// let p = unsafe {
+    ///     Imp::new_cyclic(|me| Node {
+    ///         me: me.clone(),
+    ///         value: 5,
+    ///     })
+    /// };
+    /// assert!(Imp::ptr_eq(&p.me.upgrade().unwrap(), &p));
+    /// ```
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`].
+    pub unsafe fn new_cyclic(f: impl FnOnce(&WeakImp<T>) -> T) -> Self {
+        Self {
+            v: Rc::new_cyclic(|v| RefCell::new(f(&WeakImp { v: v.clone() }))),
+        }
+    }
+
+    /// Returns a pointer to an uninitialized value, to be written through any clone of the pointer
+    /// before turning it into an `Imp<T>` with [`Imp::assume_init`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let mut p = unsafe { Imp::<i32>::new_uninit() };
+    /// let mut p2 = p.clone();
+    /// p2.write(5);
+    /// drop(p2);
+    /// let p = unsafe { Imp::assume_init(p) }.ok().unwrap();
+    /// assert_eq!(p, 5);
+    /// ```
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`].
+    pub unsafe fn new_uninit() -> Imp<MaybeUninit<T>> {
+        Imp::new(MaybeUninit::uninit())
+    }
+
     /// Returns true if two pointers are equal
     ///
     /// # Arguments
@@ -56,6 +150,244 @@ impl<T> Imp<T> {
     pub fn ptr_eq(this: &Self, other: &Self) -> bool {
         Rc::ptr_eq(&this.v, &other.v)
     }
+
+    /// Returns the inner value, if this is the only `Imp` pointing to it.
+    /// Otherwise an `Err` is returned with the same pointer that was passed in.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(5) };
+    /// assert_eq!(Imp::try_unwrap(p), Ok(5));
+    ///
+    /// let p = unsafe { Imp::new(5) };
+    /// let p2 = p.clone();
+    /// assert_eq!(Imp::try_unwrap(p), Err(p2));
+    /// ```
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        Rc::try_unwrap(this.v)
+            .map(RefCell::into_inner)
+            .map_err(|v| Self { v })
+    }
+
+    /// Returns the inner value, if this is the only `Imp` pointing to it.
+    /// Otherwise `None` is returned and the pointer is dropped.
+    ///
+    /// Unlike [`Imp::try_unwrap`], calling this on every clone guarantees that exactly one of them gets the value.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(5) };
+    /// let p2 = p.clone();
+    /// assert_eq!(Imp::into_inner(p), None);
+    /// assert_eq!(Imp::into_inner(p2), Some(5));
+    /// ```
+    pub fn into_inner(this: Self) -> Option<T> {
+        Rc::into_inner(this.v).map(RefCell::into_inner)
+    }
+}
+
+impl<T> Imp<MaybeUninit<T>> {
+    /// Turns a pointer to a value created with [`Imp::new_uninit`] into a pointer to the initialized value,
+    /// if this is the only pointer to it.
+    /// Otherwise an `Err` is returned with the same pointer that was passed in,
+    /// as other `Imp` or [`WeakImp`] pointers could still overwrite the value as a `MaybeUninit<T>`,
+    /// or be the last to drop it without running its destructor.
+    ///
+    /// # Safety
+    /// The value must have been initialized, see [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(this: Self) -> Result<Imp<T>, Self> {
+        if !Self::is_unique(&this) {
+            return Err(this);
+        }
+        // MaybeUninit<T> has the same layout as T, so the RefCells around them do too.
+        Ok(Imp {
+            v: Rc::from_raw(Rc::into_raw(this.v) as *const RefCell<T>),
+        })
+    }
+}
+
+impl<T: Clone> Imp<T> {
+    /// Returns a mutable reference to the inner value, cloning it into a new allocation first
+    /// if other `Imp` or [`WeakImp`] pointers point to it.
+    ///
+    /// Clone-on-write, see [`Rc::make_mut`].
+    ///
+    /// # Panics
+    /// Panics if the value has to be cloned while it is mutably borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let mut p = unsafe { Imp::new(5) };
+    /// let p2 = p.clone();
+    /// *Imp::make_mut(&mut p) += 1; // Clones the value, p2 is left untouched.
+    /// assert_eq!(p, 6);
+    /// assert_eq!(p2, 5);
+    /// ```
+    pub fn make_mut(this: &mut Self) -> &mut T {
+        Rc::make_mut(&mut this.v).get_mut()
+    }
+}
+
+impl<T: ?Sized> Imp<T> {
+    /// Returns a pointer sharing the value of an existing `Rc<RefCell<T>>`.
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`].
+    pub unsafe fn from_rc(rc: Rc<RefCell<T>>) -> Self {
+        Self { v: rc }
+    }
+
+    /// Creates a [`WeakImp`] pointing to the same value.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(5) };
+    /// let w = Imp::downgrade(&p);
+    /// assert!(Imp::ptr_eq(&w.upgrade().unwrap(), &p));
+    /// ```
+    pub fn downgrade(this: &Self) -> WeakImp<T> {
+        #[cfg(feature = "checked")]
+        crate::checked::release(this.v.as_ptr());
+        WeakImp {
+            v: Rc::downgrade(&this.v),
+        }
+    }
+
+    /// Returns a pointer to a part of the value, like one of its fields, keeping the whole value alive.
+    ///
+    /// The projection is applied on every access, so it keeps pointing to the same place
+    /// when the value is changed, for example into the first element of a growing `Vec`.
+    /// The projection is passed twice, as `f` for shared access and `f_mut` for mutable access, and both should
+    /// return the same part. With only `f_mut`, every read would have to create a mutable reference to the whole
+    /// value, which is undefined behavior while it is also borrowed elsewhere.
+    ///
+    /// # Arguments
+    /// * `this` - The pointer to project from
+    /// * `f` - Returns the part to point to, given the whole value, for shared access
+    /// * `f_mut` - The same as `f`, for mutable access
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new((String::new(), 5)) };
+    /// let mut s = Imp::map(&p, |t| &t.0, |t| &mut t.0);
+    /// let n = Imp::map(&p, |t| &t.1, |t| &mut t.1);
+    /// s.push_str("yo"); // Modifies the first field of p.
+    /// assert_eq!(Imp::read(&p).0, "yo");
+    /// assert_eq!(n + 1, 6);
+    /// ```
+    pub fn map<U: ?Sized, F, G>(this: &Self, f: F, f_mut: G) -> MappedImp<U>
+    where
+        T: 'static,
+        F: Fn(&T) -> &U + 'static,
+        G: Fn(&mut T) -> &mut U + 'static,
+    {
+        MappedImp::project(this.v.clone(), f, f_mut)
+    }
+
+    /// Returns the number of [`WeakImp`] pointers pointing to the value.
+    pub fn weak_count(this: &Self) -> usize {
+        Rc::weak_count(&this.v)
+    }
+
+    /// Returns the number of `Imp` pointers pointing to the value.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(5) };
+    /// let _p2 = p.clone();
+    /// assert_eq!(Imp::strong_count(&p), 2);
+    /// ```
+    pub fn strong_count(this: &Self) -> usize {
+        Rc::strong_count(&this.v)
+    }
+
+    /// Returns true if no other `Imp` or [`WeakImp`] pointers point to the value.
+    pub fn is_unique(this: &Self) -> bool {
+        Rc::strong_count(&this.v) == 1 && Rc::weak_count(&this.v) == 0
+    }
+
+    /// Returns a mutable reference to the inner value, if no other `Imp` or [`WeakImp`] pointers point to it.
+    ///
+    /// As the pointer is unique, this reference is checked by the borrow checker alone.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let mut p = unsafe { Imp::new(5) };
+    /// *Imp::get_mut(&mut p).unwrap() += 1;
+    /// let _p2 = p.clone();
+    /// assert!(Imp::get_mut(&mut p).is_none());
+    /// ```
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        Rc::get_mut(&mut this.v).map(RefCell::get_mut)
+    }
+
+    /// Immutably borrows the inner value, going through the borrow flag of the inner `RefCell`.
+    ///
+    /// This is an associated function rather than a method, so it does not shadow
+    /// methods of `T` with the same name.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    /// For a non-panicking variant, use [`Imp::try_read`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(vec![1, 2, 3]) };
+    /// assert_eq!(Imp::read(&p).len(), 3);
+    /// ```
+    #[track_caller]
+    pub fn read(this: &Self) -> ImpRef<'_, T> {
+        ImpRef::borrow(&this.v)
+    }
+
+    /// Mutably borrows the inner value, going through the borrow flag of the inner `RefCell`.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    /// For a non-panicking variant, use [`Imp::try_write`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(vec![1, 2, 3]) };
+    /// let p2 = p.clone();
+    /// Imp::write(&p).push(4);
+    /// assert_eq!(*Imp::read(&p2), [1, 2, 3, 4]);
+    /// ```
+    #[track_caller]
+    pub fn write(this: &Self) -> ImpRefMut<'_, T> {
+        ImpRefMut::borrow(&this.v)
+    }
+
+    /// Immutably borrows the inner value, returning an error if the value is currently mutably borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(5) };
+    /// let w = Imp::write(&p);
+    /// assert!(Imp::try_read(&p).is_err());
+    /// drop(w);
+    /// assert!(Imp::try_read(&p).is_ok());
+    /// ```
+    #[track_caller]
+    pub fn try_read(this: &Self) -> Result<ImpRef<'_, T>, BorrowError> {
+        ImpRef::try_borrow(&this.v)
+    }
+
+    /// Mutably borrows the inner value, returning an error if the value is currently borrowed.
+    #[track_caller]
+    pub fn try_write(this: &Self) -> Result<ImpRefMut<'_, T>, BorrowMutError> {
+        ImpRefMut::try_borrow(&this.v)
+    }
 }
 
 /*
@@ -63,10 +395,12 @@ impl<T> Imp<T> {
 */
 mod clone_impl {
     use super::Imp;
-    use std::clone::Clone;
+    use core::clone::Clone;
 
     impl<T: ?Sized> Clone for Imp<T> {
         fn clone(&self) -> Self {
+            #[cfg(feature = "checked")]
+            crate::checked::release(self.v.as_ptr());
             Self { v: self.v.clone() }
         }
     }
@@ -76,27 +410,79 @@ mod clone_impl {
     Allows access to the inner methods from T.
 */
 mod deref_impl {
-    use std::{
-        marker::Unsize,
-        ops::{CoerceUnsized, Deref, DerefMut, DispatchFromDyn},
-    };
+    use core::ops::{Deref, DerefMut};
 
     use super::Imp;
 
-    impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<Imp<U>> for Imp<T> {}
-    impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<Imp<U>> for Imp<T> {}
-
     impl<T: ?Sized> Deref for Imp<T> {
         type Target = T;
 
+        #[track_caller]
         fn deref(&self) -> &Self::Target {
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.v.as_ptr(),
+                self as *const Self as *const (),
+                crate::checked::Access::Shared,
+            );
             unsafe { &*self.v.as_ptr() }
         }
     }
 
     impl<T: ?Sized> DerefMut for Imp<T> {
+        #[track_caller]
         fn deref_mut(&mut self) -> &mut Self::Target {
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.v.as_ptr(),
+                self as *const Self as *const (),
+                crate::checked::Access::Exclusive,
+            );
             unsafe { &mut *self.v.as_ptr() }
         }
     }
 }
+
+/*
+    Allows coercing into pointers to trait objects, requires the nightly feature.
+    On stable, use the imp_dyn! macro instead.
+*/
+#[cfg(feature = "nightly")]
+mod unsize_impl {
+    use core::{
+        marker::Unsize,
+        ops::{CoerceUnsized, DispatchFromDyn},
+    };
+
+    use super::Imp;
+
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<Imp<U>> for Imp<T> {}
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<Imp<U>> for Imp<T> {}
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::rc::Rc;
+    pub use core::cell::RefCell;
+}
+
+/// Creates an `Imp` of an unsized type, such as `Imp<dyn Trait>`, from a sized value.
+///
+/// Works on stable Rust, where `Imp<T>` can not coerce into `Imp<dyn Trait>` on its own.
+/// Like [`Imp::new`], the macro has to be used in an `unsafe` block.
+///
+/// # Examples
+/// ```
+/// use core::fmt::Display;
+/// use interior_mutability_pointer::{imp_dyn, Imp};
+/// let v: Vec<Imp<dyn Display>> = vec![unsafe { imp_dyn!(5) }, unsafe { imp_dyn!("yo") }];
+/// assert_eq!(format!("{} {}", &*v[0], &*v[1]), "5 yo");
+/// ```
+#[macro_export]
+macro_rules! imp_dyn {
+    ($e:expr) => {
+        $crate::Imp::from_rc($crate::__private::Rc::new($crate::__private::RefCell::new(
+            $e,
+        )))
+    };
+}
@@ -0,0 +1,282 @@
+use alloc::rc::Rc;
+use core::cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut};
+
+use crate::{ImpRef, ImpRefMut};
+
+/// A pointer to a part of the value behind an [`Imp`](crate::Imp), like one of its fields.
+///
+/// Created with [`Imp::map`](crate::Imp::map). Keeps the whole value alive, and gives the same
+/// unchecked access to the projected part as [`Imp`](crate::Imp) does to the whole,
+/// including the operators. Clones point to the same part of the same value.
+///
+/// # Examples
+/// ```
+/// use interior_mutability_pointer::Imp;
+/// struct Player {
+///     name: String,
+///     hp: i32,
+/// }
+/// let p = unsafe { Imp::new(Player { name: "yo".into(), hp: 100 }) };
+/// let mut hp = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+/// hp -= 10;
+/// assert_eq!(p.hp, 90);
+/// ```
+pub struct MappedImp<U: ?Sized> {
+    p: Rc<dyn Project<U>>,
+}
+
+/*
+    Type-erases the root value and the projections applied to it.
+    Every projection comes as a shared and a mutable one, so shared access never creates a `&mut` to the root.
+    Like the Deref of Imp, `get` and `get_mut` run them on the unchecked pointer to the root,
+    while the guards run them on the borrow of the root RefCell.
+*/
+trait Project<U: ?Sized> {
+    fn root(&self) -> *const ();
+    fn get(&self) -> *const U;
+    fn get_mut(&self) -> *mut U;
+    fn try_borrow(&self) -> Result<Ref<'_, U>, BorrowError>;
+    fn try_borrow_mut(&self) -> Result<RefMut<'_, U>, BorrowMutError>;
+}
+
+/*
+    A projection applied directly to the root value.
+*/
+struct Projection<T: ?Sized, F, G> {
+    root: Rc<RefCell<T>>,
+    f: F,
+    f_mut: G,
+}
+
+impl<T: ?Sized, U: ?Sized, F, G> Project<U> for Projection<T, F, G>
+where
+    F: Fn(&T) -> &U,
+    G: Fn(&mut T) -> &mut U,
+{
+    fn root(&self) -> *const () {
+        self.root.as_ptr() as *const ()
+    }
+
+    fn get(&self) -> *const U {
+        (self.f)(unsafe { &*self.root.as_ptr() })
+    }
+
+    fn get_mut(&self) -> *mut U {
+        (self.f_mut)(unsafe { &mut *self.root.as_ptr() })
+    }
+
+    fn try_borrow(&self) -> Result<Ref<'_, U>, BorrowError> {
+        Ok(Ref::map(self.root.try_borrow()?, |t| (self.f)(t)))
+    }
+
+    fn try_borrow_mut(&self) -> Result<RefMut<'_, U>, BorrowMutError> {
+        Ok(RefMut::map(self.root.try_borrow_mut()?, |t| {
+            (self.f_mut)(t)
+        }))
+    }
+}
+
+/*
+    A projection applied to the result of another one.
+*/
+struct Compose<U: ?Sized, F, G> {
+    inner: Rc<dyn Project<U>>,
+    f: F,
+    f_mut: G,
+}
+
+impl<U: ?Sized, V: ?Sized, F, G> Project<V> for Compose<U, F, G>
+where
+    F: Fn(&U) -> &V,
+    G: Fn(&mut U) -> &mut V,
+{
+    fn root(&self) -> *const () {
+        self.inner.root()
+    }
+
+    fn get(&self) -> *const V {
+        (self.f)(unsafe { &*self.inner.get() })
+    }
+
+    fn get_mut(&self) -> *mut V {
+        (self.f_mut)(unsafe { &mut *self.inner.get_mut() })
+    }
+
+    fn try_borrow(&self) -> Result<Ref<'_, V>, BorrowError> {
+        Ok(Ref::map(self.inner.try_borrow()?, |u| (self.f)(u)))
+    }
+
+    fn try_borrow_mut(&self) -> Result<RefMut<'_, V>, BorrowMutError> {
+        Ok(RefMut::map(self.inner.try_borrow_mut()?, |u| {
+            (self.f_mut)(u)
+        }))
+    }
+}
+
+impl<U: ?Sized> MappedImp<U> {
+    pub(crate) fn project<T: ?Sized + 'static, F, G>(root: Rc<RefCell<T>>, f: F, f_mut: G) -> Self
+    where
+        F: Fn(&T) -> &U + 'static,
+        G: Fn(&mut T) -> &mut U + 'static,
+    {
+        #[cfg(feature = "checked")]
+        crate::checked::release(root.as_ptr());
+        Self {
+            p: Rc::new(Projection { root, f, f_mut }),
+        }
+    }
+
+    /// Projects further into the part this pointer points to.
+    ///
+    /// # Arguments
+    /// * `this` - The pointer to project from
+    /// * `f` - Returns the part to point to, given the current one, for shared access
+    /// * `f_mut` - The same as `f`, for mutable access
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::{Imp, MappedImp};
+    /// let p = unsafe { Imp::new(vec![(1, 2)]) };
+    /// let first = Imp::map(&p, |v| &v[0], |v| &mut v[0]);
+    /// let mut y = MappedImp::map(&first, |t| &t.1, |t| &mut t.1);
+    /// *y = 5;
+    /// assert_eq!(Imp::read(&p)[0], (1, 5));
+    /// ```
+    pub fn map<V: ?Sized, F, G>(this: &Self, f: F, f_mut: G) -> MappedImp<V>
+    where
+        U: 'static,
+        F: Fn(&U) -> &V + 'static,
+        G: Fn(&mut U) -> &mut V + 'static,
+    {
+        #[cfg(feature = "checked")]
+        crate::checked::release(this.p.root());
+        MappedImp {
+            p: Rc::new(Compose {
+                inner: this.p.clone(),
+                f,
+                f_mut,
+            }),
+        }
+    }
+
+    /// Returns true if both pointers point to the same part of the same value.
+    ///
+    /// # Arguments
+    /// * `this` - A pointer to compare
+    /// * `other` - The other pointer to compare to
+    ///
+    /// # Panics
+    /// Panics if one of the values is currently mutably borrowed, as the projections are run to compare them.
+    #[track_caller]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        core::ptr::addr_eq(Self::as_ptr(this), Self::as_ptr(other))
+    }
+
+    pub(crate) fn projection_ptr(this: &Self) -> *const () {
+        Rc::as_ptr(&this.p) as *const ()
+    }
+
+    /*
+        Runs the shared projection under a borrow of the root, which is released before returning.
+    */
+    #[track_caller]
+    pub(crate) fn as_ptr(this: &Self) -> *const U {
+        match this.p.try_borrow() {
+            Ok(r) => &*r as *const U,
+            Err(e) => panic!("already mutably borrowed: {:?}", e),
+        }
+    }
+
+    /// Immutably borrows the projected part, going through the borrow flag of the whole value.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    /// For a non-panicking variant, use [`MappedImp::try_read`].
+    #[track_caller]
+    pub fn read(this: &Self) -> ImpRef<'_, U> {
+        ImpRef::borrow_in(this.p.root(), this.p.try_borrow())
+    }
+
+    /// Mutably borrows the projected part, going through the borrow flag of the whole value.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    /// For a non-panicking variant, use [`MappedImp::try_write`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::{Imp, MappedImp};
+    /// let p = unsafe { Imp::new(vec![String::new()]) };
+    /// let s = Imp::map(&p, |v| &v[0], |v| &mut v[0]);
+    /// MappedImp::write(&s).push_str("yo");
+    /// assert_eq!(*Imp::read(&p), ["yo"]);
+    /// ```
+    #[track_caller]
+    pub fn write(this: &Self) -> ImpRefMut<'_, U> {
+        ImpRefMut::borrow_in(this.p.root(), this.p.try_borrow_mut())
+    }
+
+    /// Immutably borrows the projected part, returning an error if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn try_read(this: &Self) -> Result<ImpRef<'_, U>, BorrowError> {
+        ImpRef::try_borrow_in(this.p.root(), this.p.try_borrow())
+    }
+
+    /// Mutably borrows the projected part, returning an error if the value is currently borrowed.
+    #[track_caller]
+    pub fn try_write(this: &Self) -> Result<ImpRefMut<'_, U>, BorrowMutError> {
+        ImpRefMut::try_borrow_in(this.p.root(), this.p.try_borrow_mut())
+    }
+}
+
+/*
+    Implements cloning the pointer, sharing the projection.
+*/
+mod clone_impl {
+    use super::MappedImp;
+
+    impl<U: ?Sized> Clone for MappedImp<U> {
+        fn clone(&self) -> Self {
+            #[cfg(feature = "checked")]
+            crate::checked::release(self.p.root());
+            Self { p: self.p.clone() }
+        }
+    }
+}
+
+/*
+    Allows access to the inner methods of the projected part, the same way as Imp.
+*/
+mod deref_impl {
+    use core::ops::{Deref, DerefMut};
+
+    use super::MappedImp;
+
+    impl<U: ?Sized> Deref for MappedImp<U> {
+        type Target = U;
+
+        #[track_caller]
+        fn deref(&self) -> &Self::Target {
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.p.root(),
+                self as *const Self as *const (),
+                crate::checked::Access::Shared,
+            );
+            unsafe { &*self.p.get() }
+        }
+    }
+
+    impl<U: ?Sized> DerefMut for MappedImp<U> {
+        #[track_caller]
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.p.root(),
+                self as *const Self as *const (),
+                crate::checked::Access::Exclusive,
+            );
+            unsafe { &mut *self.p.get_mut() }
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use alloc::{
+    boxed::Box,
+    rc::{Rc, Weak},
+    vec::Vec,
+};
+use core::{
+    cell::{BorrowError, BorrowMutError, Cell, RefCell},
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{ImpRef, ImpRefMut};
+
+/// A pointer like [`Imp`](crate::Imp), which tracks changes to its value and notifies subscribers of them.
+///
+/// Every mutable access, through `DerefMut`, `IndexMut`, [`ObservableImp::write`] or the compound assignment
+/// operators, bumps the [version](ObservableImp::version) of the value and notifies the subscribers
+/// registered with [`ObservableImp::subscribe`].
+///
+/// Mutations through a guard or an operator notify as soon as they are done.
+/// `DerefMut` and `IndexMut` can not know when the returned reference is no longer used,
+/// so their notification is delivered on the next access to the value through any of its pointers,
+/// at the end of a [batch](ObservableImp::batch), or by [`ObservableImp::flush`].
+///
+/// # Examples
+/// ```
+/// use std::{cell::Cell, rc::Rc};
+/// use interior_mutability_pointer::ObservableImp;
+/// let mut p = unsafe { ObservableImp::new(5) };
+/// let seen = Rc::new(Cell::new(0));
+/// let s = seen.clone();
+/// let _sub = ObservableImp::subscribe(&p, move |v| s.set(*v));
+/// p += 1;
+/// assert_eq!(seen.get(), 6);
+/// assert_eq!(ObservableImp::version(&p), 1);
+/// ```
+pub struct ObservableImp<T: ?Sized> {
+    v: Rc<Observed<T>>,
+}
+
+type Subscribers<T> = Vec<(u64, Rc<dyn Fn(&T)>)>;
+
+struct Observed<T: ?Sized> {
+    // Shared with the computed values reading this one, so they can tell when it changed.
+    version: Rc<Cell<u64>>,
+    // A change has been made, which the subscribers have not been notified of yet.
+    pending: Cell<bool>,
+    batch: Cell<usize>,
+    next_id: Cell<u64>,
+    subscribers: RefCell<Subscribers<T>>,
+    value: RefCell<T>,
+}
+
+impl<T: ?Sized> Observed<T> {
+    fn bump(&self) {
+        self.version.set(self.version.get() + 1);
+        #[cfg(feature = "std")]
+        crate::computed::changed();
+    }
+
+    /*
+        Records a finished change, notifying right away unless in a batch.
+    */
+    fn changed(&self) {
+        self.pending.set(true);
+        self.settle();
+    }
+
+    /*
+        Settles the value before it is read, and records the read if a computed value is being evaluated.
+    */
+    fn read(&self) {
+        self.settle();
+        #[cfg(feature = "std")]
+        crate::computed::track(&self.version);
+    }
+
+    /*
+        Delivers a pending notification, unless in a batch.
+    */
+    fn settle(&self) {
+        if self.pending.get() && self.batch.get() == 0 {
+            self.notify();
+        }
+    }
+
+    fn notify(&self) {
+        // While the value is mutably borrowed the notification stays pending, to be delivered on a later access.
+        let Ok(value) = self.value.try_borrow() else {
+            return;
+        };
+        self.pending.set(false);
+        // Cloned, so the callbacks can subscribe and unsubscribe.
+        let subscribers: Vec<_> = self
+            .subscribers
+            .borrow()
+            .iter()
+            .map(|(_, f)| f.clone())
+            .collect();
+        for f in subscribers {
+            f(&value);
+        }
+    }
+}
+
+impl<T> ObservableImp<T> {
+    /// Returns an observable pointer to the data
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The value to be pointed to.
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`](crate::Imp::new).
+    pub unsafe fn new(t: T) -> Self {
+        Self {
+            v: Rc::new(Observed {
+                version: Rc::new(Cell::new(0)),
+                pending: Cell::new(false),
+                batch: Cell::new(0),
+                next_id: Cell::new(0),
+                subscribers: RefCell::new(Vec::new()),
+                value: RefCell::new(t),
+            }),
+        }
+    }
+}
+
+impl<T: ?Sized> ObservableImp<T> {
+    /// Returns the number of mutable accesses made to the value, through any of its pointers.
+    ///
+    /// Allows polling for changes, by comparing with a version seen earlier.
+    pub fn version(this: &Self) -> u64 {
+        this.v.version.get()
+    }
+
+    /// Registers `f` to be called with the value after every change.
+    /// `f` is unregistered when the returned [`Subscription`] is dropped.
+    ///
+    /// The value is borrowed while `f` runs, so mutating it from `f` panics.
+    ///
+    /// # Arguments
+    /// * `this` - The pointer to the value to observe
+    /// * `f` - Called with the value after each change
+    pub fn subscribe(this: &Self, f: impl Fn(&T) + 'static) -> Subscription
+    where
+        T: 'static,
+    {
+        let id = this.v.next_id.get();
+        this.v.next_id.set(id + 1);
+        this.v.subscribers.borrow_mut().push((id, Rc::new(f)));
+        let v: Weak<Observed<T>> = Rc::downgrade(&this.v);
+        Subscription {
+            unsubscribe: Some(Box::new(move || {
+                if let Some(v) = v.upgrade() {
+                    v.subscribers.borrow_mut().retain(|(i, _)| *i != id);
+                }
+            })),
+        }
+    }
+
+    /// Runs `f`, holding back the notifications for the value until it returns.
+    /// The subscribers are then notified once, if the value was changed.
+    ///
+    /// Batches can be nested, and only the outermost one notifies.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    /// use interior_mutability_pointer::ObservableImp;
+    /// let mut p = unsafe { ObservableImp::new(vec![]) };
+    /// let calls = Rc::new(Cell::new(0));
+    /// let c = calls.clone();
+    /// let _sub = ObservableImp::subscribe(&p, move |_| c.set(c.get() + 1));
+    /// ObservableImp::batch(&p.clone(), || {
+    ///     p.push(1);
+    ///     p.push(2);
+    /// });
+    /// assert_eq!(calls.get(), 1);
+    /// ```
+    pub fn batch<R>(this: &Self, f: impl FnOnce() -> R) -> R {
+        struct End<'a>(&'a Cell<usize>);
+        impl Drop for End<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() - 1);
+            }
+        }
+
+        this.v.settle();
+        this.v.batch.set(this.v.batch.get() + 1);
+        let r = {
+            let _end = End(&this.v.batch);
+            f()
+        };
+        this.v.settle();
+        r
+    }
+
+    /// Notifies the subscribers of a change made through `DerefMut` or `IndexMut`, without waiting for the next access.
+    pub fn flush(this: &Self) {
+        this.v.settle();
+    }
+
+    /// Immutably borrows the inner value, going through the borrow flag of the inner `RefCell`.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn read(this: &Self) -> ImpRef<'_, T> {
+        this.v.read();
+        ImpRef::borrow(&this.v.value)
+    }
+
+    /// Mutably borrows the inner value, notifying the subscribers when the guard is dropped.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn write(this: &Self) -> ObservableRefMut<'_, T> {
+        this.v.settle();
+        let r = ImpRefMut::borrow(&this.v.value);
+        this.v.bump();
+        ObservableRefMut {
+            r: ManuallyDrop::new(r),
+            v: &this.v,
+        }
+    }
+
+    /// Immutably borrows the inner value, returning an error if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn try_read(this: &Self) -> Result<ImpRef<'_, T>, BorrowError> {
+        this.v.read();
+        ImpRef::try_borrow(&this.v.value)
+    }
+
+    /// Mutably borrows the inner value, returning an error if the value is currently borrowed.
+    #[track_caller]
+    pub fn try_write(this: &Self) -> Result<ObservableRefMut<'_, T>, BorrowMutError> {
+        this.v.settle();
+        let r = ImpRefMut::try_borrow(&this.v.value)?;
+        this.v.bump();
+        Ok(ObservableRefMut {
+            r: ManuallyDrop::new(r),
+            v: &this.v,
+        })
+    }
+
+    /// Returns true if two pointers point to the same value.
+    ///
+    /// # Arguments
+    /// * `this` - A pointer to compare
+    /// * `other` - The other pointer to compare to
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Rc::ptr_eq(&this.v, &other.v)
+    }
+
+    pub(crate) fn as_ptr(this: &Self) -> *const T {
+        this.v.value.as_ptr()
+    }
+}
+
+/// An exclusive borrow of the value behind an [`ObservableImp`],
+/// which notifies the subscribers of the value when dropped.
+pub struct ObservableRefMut<'a, T: ?Sized> {
+    r: ManuallyDrop<ImpRefMut<'a, T>>,
+    v: &'a Observed<T>,
+}
+
+impl<T: ?Sized> Drop for ObservableRefMut<'_, T> {
+    fn drop(&mut self) {
+        // The borrow is released first, so the subscribers can read the value.
+        unsafe { ManuallyDrop::drop(&mut self.r) };
+        self.v.changed();
+    }
+}
+
+impl<T: ?Sized> Deref for ObservableRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.r
+    }
+}
+
+impl<T: ?Sized> DerefMut for ObservableRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.r
+    }
+}
+
+/// Keeps a callback registered with [`ObservableImp::subscribe`], unregistering it when dropped.
+#[must_use = "the callback is unregistered when the subscription is dropped"]
+pub struct Subscription {
+    unsubscribe: Option<Box<dyn FnOnce()>>,
+}
+
+impl Subscription {
+    /// Keeps the callback registered for as long as the value lives.
+    pub fn detach(mut self) {
+        self.unsubscribe = None;
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.unsubscribe.take() {
+            unsubscribe();
+        }
+    }
+}
+
+/*
+    Implements cloning the pointer.
+*/
+mod clone_impl {
+    use super::ObservableImp;
+
+    impl<T: ?Sized> Clone for ObservableImp<T> {
+        fn clone(&self) -> Self {
+            #[cfg(feature = "checked")]
+            crate::checked::release(self.v.value.as_ptr());
+            Self { v: self.v.clone() }
+        }
+    }
+}
+
+/*
+    Allows access to the inner methods from T, the same way as Imp.
+    DerefMut counts as a change, which is notified on the next access as the end of it can not be known.
+*/
+mod deref_impl {
+    use core::ops::{Deref, DerefMut};
+
+    use super::ObservableImp;
+
+    impl<T: ?Sized> Deref for ObservableImp<T> {
+        type Target = T;
+
+        #[track_caller]
+        fn deref(&self) -> &Self::Target {
+            self.v.read();
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.v.value.as_ptr(),
+                self as *const Self as *const (),
+                crate::checked::Access::Shared,
+            );
+            unsafe { &*self.v.value.as_ptr() }
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for ObservableImp<T> {
+        #[track_caller]
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            self.v.settle();
+            #[cfg(feature = "checked")]
+            crate::checked::deref(
+                self.v.value.as_ptr(),
+                self as *const Self as *const (),
+                crate::checked::Access::Exclusive,
+            );
+            self.v.bump();
+            self.v.pending.set(true);
+            unsafe { &mut *self.v.value.as_ptr() }
+        }
+    }
+}
+
+/*
+    Allows printing the guard, the same way as ImpRefMut.
+*/
+mod debug_impl {
+    use core::fmt::Debug;
+
+    use super::ObservableRefMut;
+
+    impl<T: ?Sized + Debug> Debug for ObservableRefMut<'_, T> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+}
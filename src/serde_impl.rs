@@ -0,0 +1,544 @@
+/*
+    Serde support, enabled by the `serde` feature.
+
+    By default every pointer (de)serializes transparently as its inner value, so clones of one pointer are written
+    out once per clone and come back as separate allocations.
+    Deserializing creates new pointers, so it is only allowed for `Imp` and `WeakImp` within `deserialize_unchecked`.
+
+    Within `identity::scope` the `Rc` based pointers are written as `{ id, value }` instead,
+    with the value only present the first time an allocation is seen,
+    so shared values and weak back references survive the round trip.
+*/
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ImpRef;
+
+fn serialize_rc<T: ?Sized + Serialize, S: Serializer>(
+    v: &Rc<RefCell<T>>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    #[cfg(feature = "std")]
+    if let Some((id, first)) = identity::visit(v) {
+        use serde::ser::SerializeStruct;
+
+        let value = if first { Some(ImpRef::borrow(v)) } else { None };
+        let mut st = s.serialize_struct("Imp", 2)?;
+        st.serialize_field("id", &id)?;
+        st.serialize_field("value", &value.as_deref())?;
+        return st.end();
+    }
+    ImpRef::borrow(v).serialize(s)
+}
+
+fn deserialize_rc<'de, T: Deserialize<'de> + 'static, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Rc<RefCell<T>>, D::Error> {
+    #[cfg(feature = "std")]
+    if identity::active() {
+        return identity::deserialize_strong(d);
+    }
+    T::deserialize(d).map(|t| Rc::new(RefCell::new(t)))
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static UNCHECKED: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// Runs `f` with deserializing into [`Imp`](crate::Imp) and [`WeakImp`](crate::WeakImp) allowed on the current thread.
+/// Outside of it, deserializing them returns an error.
+///
+/// Requires the `std` feature.
+///
+/// # Safety
+/// Every `Imp` deserialized within `f`, and every `Imp` upgraded from a deserialized `WeakImp`,
+/// has the same requirements as one created by [`Imp::new`](crate::Imp::new).
+///
+/// # Examples
+/// ```
+/// use interior_mutability_pointer::{deserialize_unchecked, Imp};
+/// let p: Imp<Vec<i32>> = unsafe { deserialize_unchecked(|| serde_json::from_str("[1, 2]")) }.unwrap();
+/// assert_eq!(p, vec![1, 2]);
+/// assert!(serde_json::from_str::<Imp<i32>>("5").is_err());
+/// ```
+#[cfg(feature = "std")]
+pub unsafe fn deserialize_unchecked<R>(f: impl FnOnce() -> R) -> R {
+    struct End;
+    impl Drop for End {
+        fn drop(&mut self) {
+            UNCHECKED.with(|u| u.set(u.get() - 1));
+        }
+    }
+
+    UNCHECKED.with(|u| u.set(u.get() + 1));
+    let _end = End;
+    f()
+}
+
+#[cfg(feature = "std")]
+fn check_unchecked<E: serde::de::Error>(name: &str) -> Result<(), E> {
+    if UNCHECKED.with(|u| u.get()) == 0 {
+        return Err(E::custom(format_args!(
+            "{} can only be deserialized within deserialize_unchecked, use GuardedImp otherwise",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/*
+    Allows serializing the pointers as their inner value, or by identity within identity::scope.
+*/
+mod serialize_impl {
+    use serde::{Serialize, Serializer};
+
+    use super::serialize_rc;
+    use crate::{GuardedImp, Imp};
+    #[cfg(feature = "std")]
+    use crate::{MutexImp, SyncImp};
+
+    impl<T: ?Sized + Serialize> Serialize for Imp<T> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            serialize_rc(&self.v, s)
+        }
+    }
+
+    impl<T: ?Sized + Serialize> Serialize for GuardedImp<T> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            serialize_rc(&self.v, s)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: ?Sized + Serialize> Serialize for SyncImp<T> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            self.read().serialize(s)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: ?Sized + Serialize> Serialize for MutexImp<T> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            self.lock().serialize(s)
+        }
+    }
+}
+
+/*
+    Allows deserializing the pointers from their inner value, or by identity within identity::scope.
+    The `'static` bound is required for the latter, and kept without `std` so enabling it only adds impls.
+    Imp is only deserialized within deserialize_unchecked, which needs `std` for its thread local.
+*/
+mod deserialize_impl {
+    use serde::{Deserialize, Deserializer};
+
+    use super::deserialize_rc;
+    use crate::GuardedImp;
+    #[cfg(feature = "std")]
+    use crate::{Imp, MutexImp, SyncImp};
+
+    #[cfg(feature = "std")]
+    impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de> for Imp<T> {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            super::check_unchecked("Imp")?;
+            deserialize_rc(d).map(|v| Imp { v })
+        }
+    }
+
+    impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de> for GuardedImp<T> {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            deserialize_rc(d).map(|v| GuardedImp { v })
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de> for SyncImp<T> {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            T::deserialize(d).map(SyncImp::new)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de> for MutexImp<T> {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            T::deserialize(d).map(MutexImp::new)
+        }
+    }
+}
+
+/// Identity-preserving serialization of shared values.
+///
+/// Within [`scope`](crate::identity::scope), [`Imp`](crate::Imp), [`GuardedImp`](crate::GuardedImp)
+/// and [`WeakImp`](crate::WeakImp) are serialized as `{ id, value }`,
+/// where the value is only written the first time its allocation is seen.
+/// Deserializing within a scope turns every id back into a single allocation, so clones come back shared,
+/// and cycles through [`WeakImp`](crate::WeakImp) back references are restored.
+///
+/// `WeakImp` can only be (de)serialized within a scope, as it has no value of its own to fall back to.
+/// A cycle of strong pointers can be serialized, but deserializing it returns an error.
+///
+/// [`SyncImp`](crate::SyncImp) and [`MutexImp`](crate::MutexImp) are always (de)serialized as their inner value.
+#[cfg(feature = "std")]
+pub mod identity {
+    use alloc::{
+        boxed::Box,
+        rc::{Rc, Weak},
+        string::String,
+    };
+    use core::{any::Any, cell::RefCell, fmt, marker::PhantomData, mem::MaybeUninit};
+    use std::collections::HashMap;
+
+    use serde::{
+        de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+        ser::{self, Serialize, Serializer},
+        Deserialize, Deserializer,
+    };
+
+    use crate::WeakImp;
+
+    const FIELDS: &[&str] = &["id", "value"];
+
+    enum Slot {
+        // A Weak<RefCell<T>> to a value that is still being deserialized.
+        Building(Box<dyn Any>),
+        // An Rc<RefCell<T>>, kept alive until the scope ends so later weak references can still reach it.
+        Done(Box<dyn Any>),
+    }
+
+    // Any Weak<RefCell<T>>, keeping an allocation from being freed and its address reused.
+    trait Held {}
+
+    impl<T: ?Sized> Held for Weak<RefCell<T>> {}
+
+    #[derive(Default)]
+    struct Session {
+        ids: HashMap<usize, u64>,
+        held: Vec<Box<dyn Held>>,
+        slots: HashMap<u64, Slot>,
+    }
+
+    std::thread_local! {
+        static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+    }
+
+    /// Runs `f` with identity-preserving (de)serialization enabled on the current thread.
+    ///
+    /// Ids are shared by everything (de)serialized within the same scope, nested scopes join the outer one.
+    /// Deserialized values are kept alive until the scope ends.
+    ///
+    /// # Arguments
+    /// * `f` - The closure doing the (de)serialization
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::{deserialize_unchecked, identity, Imp};
+    /// let p = unsafe { Imp::new(5) };
+    /// let json = identity::scope(|| serde_json::to_string(&vec![p.clone(), p]).unwrap());
+    /// assert_eq!(json, r#"[{"id":0,"value":5},{"id":0,"value":null}]"#);
+    ///
+    /// let v: Vec<Imp<i32>> =
+    ///     identity::scope(|| unsafe { deserialize_unchecked(|| serde_json::from_str(&json)) }).unwrap();
+    /// assert!(Imp::ptr_eq(&v[0], &v[1]));
+    /// ```
+    pub fn scope<R>(f: impl FnOnce() -> R) -> R {
+        if active() {
+            return f();
+        }
+
+        struct End;
+        impl Drop for End {
+            fn drop(&mut self) {
+                // Dropped outside of the borrow, as dropping the kept values may run arbitrary code.
+                let session = SESSION.with(|s| s.borrow_mut().take());
+                drop(session);
+            }
+        }
+
+        SESSION.with(|s| *s.borrow_mut() = Some(Session::default()));
+        let _end = End;
+        f()
+    }
+
+    pub(crate) fn active() -> bool {
+        SESSION.with(|s| s.borrow().is_some())
+    }
+
+    fn with<R>(f: impl FnOnce(&mut Session) -> R) -> Option<R> {
+        SESSION.with(|s| s.borrow_mut().as_mut().map(f))
+    }
+
+    /*
+        Returns the id of the allocation, and whether this is the first time it is seen.
+        Returns None outside of a scope.
+    */
+    pub(crate) fn visit<T: ?Sized>(v: &Rc<RefCell<T>>) -> Option<(u64, bool)> {
+        with(|s| {
+            let next = s.ids.len() as u64;
+            let id = *s
+                .ids
+                .entry(Rc::as_ptr(v) as *const () as usize)
+                .or_insert(next);
+            if id == next {
+                let held: Box<dyn Held + '_> = Box::new(Rc::downgrade(v));
+                // SAFETY: Only the lifetime is erased. Dropping a Weak never touches the value,
+                // only the reference counts and the allocation, so it is fine if T borrowed from data that is gone.
+                s.held.push(unsafe {
+                    core::mem::transmute::<Box<dyn Held + '_>, Box<dyn Held>>(held)
+                });
+            }
+            (id, id == next)
+        })
+    }
+
+    enum Restored<T> {
+        Strong(Rc<RefCell<T>>),
+        Weak(Weak<RefCell<T>>),
+    }
+
+    fn mismatch<E: de::Error>(id: u64) -> E {
+        E::custom(format_args!("id {} refers to a value of another type", id))
+    }
+
+    /*
+        Resolves a reference to an id whose value was written earlier.
+    */
+    fn lookup<T: 'static, E: de::Error>(id: u64, weak: bool) -> Result<Restored<T>, E> {
+        with(|s| match s.slots.get(&id) {
+            None => Err(E::custom(format_args!("reference to unknown id {}", id))),
+            Some(Slot::Building(w)) if weak => w
+                .downcast_ref::<Weak<RefCell<T>>>()
+                .map(|w| Restored::Weak(w.clone()))
+                .ok_or_else(|| mismatch(id)),
+            Some(Slot::Building(_)) => Err(E::custom(format_args!(
+                "strong reference cycle through id {}, use WeakImp for back references",
+                id
+            ))),
+            Some(Slot::Done(rc)) => rc
+                .downcast_ref::<Rc<RefCell<T>>>()
+                .map(|rc| {
+                    if weak {
+                        Restored::Weak(Rc::downgrade(rc))
+                    } else {
+                        Restored::Strong(rc.clone())
+                    }
+                })
+                .ok_or_else(|| mismatch(id)),
+        })
+        .unwrap_or_else(|| Err(E::custom("identity session ended")))
+    }
+
+    /*
+        Deserializes the value of an id, handing out weak pointers to it while it is being built.
+    */
+    fn build<'de, T: Deserialize<'de> + 'static, D: Deserializer<'de>>(
+        id: u64,
+        weak: bool,
+        d: D,
+    ) -> Result<Restored<T>, D::Error> {
+        if with(|s| s.slots.contains_key(&id)).unwrap_or(false) {
+            return Err(de::Error::custom(format_args!(
+                "duplicate value for id {}",
+                id
+            )));
+        }
+
+        // The value is built within new_cyclic so the weak pointers can not be upgraded before it is written.
+        let mut error = None;
+        let rc = Rc::<MaybeUninit<RefCell<T>>>::new_cyclic(|w| {
+            // SAFETY: MaybeUninit<RefCell<T>> has the same size and alignment as RefCell<T>.
+            let w = unsafe { Weak::from_raw(Weak::into_raw(w.clone()).cast::<RefCell<T>>()) };
+            with(|s| s.slots.insert(id, Slot::Building(Box::new(w))));
+            match T::deserialize(d) {
+                Ok(t) => MaybeUninit::new(RefCell::new(t)),
+                Err(e) => {
+                    error = Some(e);
+                    MaybeUninit::uninit()
+                }
+            }
+        });
+        if let Some(e) = error {
+            with(|s| s.slots.remove(&id));
+            return Err(e);
+        }
+
+        // SAFETY: The value was written, as deserializing it did not fail.
+        let rc = unsafe { rc.assume_init() };
+        with(|s| s.slots.insert(id, Slot::Done(Box::new(rc.clone()))));
+        Ok(if weak {
+            Restored::Weak(Rc::downgrade(&rc))
+        } else {
+            Restored::Strong(rc)
+        })
+    }
+
+    /*
+        Deserializes the optional value following an id.
+    */
+    struct ValueSeed<T> {
+        id: u64,
+        weak: bool,
+        marker: PhantomData<T>,
+    }
+
+    impl<'de, T: Deserialize<'de> + 'static> DeserializeSeed<'de> for ValueSeed<T> {
+        type Value = Restored<T>;
+
+        fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+            d.deserialize_option(self)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de> + 'static> Visitor<'de> for ValueSeed<T> {
+        type Value = Restored<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an optional shared value")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            lookup(self.id, self.weak)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            lookup(self.id, self.weak)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+            build(self.id, self.weak, d)
+        }
+    }
+
+    /*
+        Deserializes an `{ id, value }` pair.
+    */
+    struct SharedVisitor<T> {
+        weak: bool,
+        marker: PhantomData<T>,
+    }
+
+    impl<T> SharedVisitor<T> {
+        fn seed(&self, id: u64) -> ValueSeed<T> {
+            ValueSeed {
+                id,
+                weak: self.weak,
+                marker: PhantomData,
+            }
+        }
+    }
+
+    impl<'de, T: Deserialize<'de> + 'static> Visitor<'de> for SharedVisitor<T> {
+        type Value = Restored<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a shared value with its id")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let id = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            seq.next_element_seed(self.seed(id))?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut id = None;
+            let mut value = None;
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "id" => id = Some(map.next_value()?),
+                    "value" => {
+                        let id =
+                            id.ok_or_else(|| de::Error::custom("`id` must precede `value`"))?;
+                        value = Some(map.next_value_seed(self.seed(id))?);
+                    }
+                    _ => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+            }
+            match (id, value) {
+                (_, Some(value)) => Ok(value),
+                // A missing value is the same as an empty one.
+                (Some(id), None) => lookup(id, self.weak),
+                (None, None) => Err(de::Error::missing_field("id")),
+            }
+        }
+    }
+
+    pub(crate) fn deserialize_strong<'de, T: Deserialize<'de> + 'static, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Rc<RefCell<T>>, D::Error> {
+        let visitor = SharedVisitor {
+            weak: false,
+            marker: PhantomData,
+        };
+        match d.deserialize_struct("Imp", FIELDS, visitor)? {
+            Restored::Strong(rc) => Ok(rc),
+            Restored::Weak(_) => unreachable!("strong references are never restored as weak"),
+        }
+    }
+
+    /*
+        Allows (de)serializing weak pointers within a scope, as an optional shared value.
+    */
+    impl<T: ?Sized + Serialize> Serialize for WeakImp<T> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            if !active() {
+                return Err(ser::Error::custom(
+                    "WeakImp can only be serialized within identity::scope",
+                ));
+            }
+            match self.upgrade() {
+                Some(imp) => s.serialize_some(&imp),
+                None => s.serialize_none(),
+            }
+        }
+    }
+
+    struct WeakVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de> + 'static> Visitor<'de> for WeakVisitor<T> {
+        type Value = WeakImp<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an optional shared value with its id")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(WeakImp::new())
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(WeakImp::new())
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+            let visitor = SharedVisitor {
+                weak: true,
+                marker: PhantomData,
+            };
+            Ok(match d.deserialize_struct("Imp", FIELDS, visitor)? {
+                Restored::Strong(rc) => WeakImp {
+                    v: Rc::downgrade(&rc),
+                },
+                Restored::Weak(v) => WeakImp { v },
+            })
+        }
+    }
+
+    impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de> for WeakImp<T> {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            if !active() {
+                return Err(de::Error::custom(
+                    "WeakImp can only be deserialized within identity::scope",
+                ));
+            }
+            super::check_unchecked("WeakImp")?;
+            d.deserialize_option(WeakVisitor(PhantomData))
+        }
+    }
+}
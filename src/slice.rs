@@ -0,0 +1,174 @@
+use alloc::{alloc::Layout, rc::Rc, string::String, vec::Vec};
+use core::{cell::RefCell, mem, mem::MaybeUninit, ptr};
+
+use crate::Imp;
+
+/*
+    Moves the elements of a Vec into a new `Rc<RefCell<[T]>>`.
+
+    An unsized RefCell can only be created through an unsizing coercion, which needs the length at compile time.
+    So the value is laid out by hand, using that a `RefCell<[T; 0]>` has the same layout as an empty `RefCell<[T]>`:
+    the elements start where the empty array does, and the size is rounded up to the alignment like for any slice tail.
+
+    The Rc is allocated as a slice of words with the size and alignment of that `RefCell<[T; 0]>`,
+    so it has the same layout as the `Rc<RefCell<[T]>>` it is turned into and the elements are only copied once.
+*/
+pub(crate) fn rc_from_vec<T>(mut v: Vec<T>) -> Rc<RefCell<[T]>> {
+    type Word<T> = RefCell<[T; 0]>;
+    assert_eq!(mem::size_of::<Word<T>>(), mem::align_of::<Word<T>>());
+
+    let empty: &RefCell<[T]> = &RefCell::new([]);
+    let offset =
+        empty.as_ptr() as *const u8 as usize - empty as *const RefCell<[T]> as *const u8 as usize;
+    let size = Layout::from_size_align(
+        offset + mem::size_of::<T>() * v.len(),
+        mem::align_of_val(empty),
+    )
+    .expect("slice too large")
+    .pad_to_align()
+    .size();
+
+    let mut words = Rc::<[Word<T>]>::new_uninit_slice(size / mem::size_of::<Word<T>>());
+    let mem = Rc::get_mut(&mut words).unwrap().as_mut_ptr() as *mut u8;
+    unsafe {
+        // Writes the borrow flag, the elements are copied after it.
+        ptr::write(mem as *mut Word<T>, RefCell::new([]));
+        ptr::copy_nonoverlapping(v.as_ptr(), mem.add(offset) as *mut T, v.len());
+        // The elements are owned by the Rc now, only the buffer is left to free.
+        let len = v.len();
+        v.set_len(0);
+        let words = Rc::into_raw(words) as *const MaybeUninit<Word<T>>;
+        let cell = ptr::slice_from_raw_parts(words as *const T, len) as *const RefCell<[T]>;
+        debug_assert_eq!(mem::size_of_val(&*cell), size);
+        Rc::from_raw(cell)
+    }
+}
+
+/*
+    Moves the contents of a String into a new `Rc<RefCell<str>>`.
+*/
+pub(crate) fn rc_from_string(s: String) -> Rc<RefCell<str>> {
+    let bytes = rc_from_vec(s.into_bytes());
+    // str has the same layout as [u8], and the bytes came from a String so they are valid UTF-8.
+    unsafe { Rc::from_raw(Rc::into_raw(bytes) as *const RefCell<str>) }
+}
+
+impl<T> Imp<[T]> {
+    /// Returns a pointer to a slice, moving the elements out of a `Vec`.
+    ///
+    /// # Arguments
+    /// * `v` - The elements to be pointed to
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let mut p = unsafe { Imp::from_vec(vec![3, 1, 2]) };
+    /// p.sort();
+    /// assert_eq!(&*p, [1, 2, 3]);
+    /// ```
+    pub unsafe fn from_vec(v: Vec<T>) -> Self {
+        Self { v: rc_from_vec(v) }
+    }
+
+    /// Returns a pointer to a slice, collecting the elements of an iterator.
+    ///
+    /// # Arguments
+    /// * `iter` - The elements to be pointed to
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::from_iter((1..4).map(|i| i * 2)) };
+    /// assert_eq!(&*p, [2, 4, 6]);
+    /// ```
+    pub unsafe fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_vec(Vec::from_iter(iter))
+    }
+}
+
+impl Imp<str> {
+    /// Returns a pointer to a string slice, moving the contents out of a `String`.
+    ///
+    /// # Arguments
+    /// * `s` - The string to be pointed to
+    ///
+    /// # Safety
+    /// Same as [`Imp::new`].
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let mut p = unsafe { Imp::from_string("yo".into()) };
+    /// p.make_ascii_uppercase();
+    /// assert_eq!(&*p, "YO");
+    /// ```
+    pub unsafe fn from_string(s: String) -> Self {
+        Self {
+            v: rc_from_string(s),
+        }
+    }
+}
+
+/*
+    Allows creating guarded pointers to slices and string slices,
+    without the extra indirection of a Vec or String.
+    Imp only gets the unsafe constructors above, as a From impl would skip Imp::new.
+    Boxed trait objects are not supported, as moving them into a RefCell needs the unstable pointer metadata API;
+    imp_dyn! builds those through an unsizing coercion instead.
+*/
+mod from_impl {
+    use alloc::{boxed::Box, string::String, vec::Vec};
+
+    use super::{rc_from_string, rc_from_vec};
+    use crate::GuardedImp;
+
+    impl<T> From<Vec<T>> for GuardedImp<[T]> {
+        fn from(v: Vec<T>) -> Self {
+            Self { v: rc_from_vec(v) }
+        }
+    }
+
+    impl<T> From<Box<[T]>> for GuardedImp<[T]> {
+        fn from(b: Box<[T]>) -> Self {
+            Self::from(b.into_vec())
+        }
+    }
+
+    impl<T: Clone> From<&[T]> for GuardedImp<[T]> {
+        fn from(v: &[T]) -> Self {
+            Self::from(v.to_vec())
+        }
+    }
+
+    impl<T> FromIterator<T> for GuardedImp<[T]> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            Self::from(Vec::from_iter(iter))
+        }
+    }
+
+    impl From<String> for GuardedImp<str> {
+        fn from(s: String) -> Self {
+            Self {
+                v: rc_from_string(s),
+            }
+        }
+    }
+
+    impl From<Box<str>> for GuardedImp<str> {
+        fn from(b: Box<str>) -> Self {
+            Self::from(b.into_string())
+        }
+    }
+
+    impl From<&str> for GuardedImp<str> {
+        fn from(s: &str) -> Self {
+            Self::from(String::from(s))
+        }
+    }
+}
@@ -0,0 +1,266 @@
+/*
+    Thread-safe handles, sharing their value through an Arc instead of an Rc.
+
+    A lock can not be held across a plain `Deref`, so these handles only give access to their value through
+    lock guards. Poisoned locks are recovered, as with `Imp` a panic while mutating does not prevent later access.
+*/
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        TryLockError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Error returned by the non-blocking and timed lock methods of [`SyncImp`] and [`MutexImp`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LockError {
+    /// The lock is currently held, so it could not be acquired without blocking.
+    WouldBlock,
+    /// The lock was still held when the timeout ran out.
+    TimedOut,
+}
+
+impl Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::WouldBlock => write!(f, "lock is held, acquiring it would block"),
+            LockError::TimedOut => write!(f, "timed out waiting for lock"),
+        }
+    }
+}
+
+impl Error for LockError {}
+
+/*
+    Turns a try-lock result into ours, recovering poisoned locks.
+*/
+fn try_lock<G>(r: Result<G, TryLockError<G>>) -> Result<G, LockError> {
+    match r {
+        Ok(g) => Ok(g),
+        Err(TryLockError::Poisoned(p)) => Ok(p.into_inner()),
+        Err(TryLockError::WouldBlock) => Err(LockError::WouldBlock),
+    }
+}
+
+/*
+    Retries a try-lock until it succeeds or the timeout runs out, backing off between attempts.
+*/
+fn lock_timeout<G>(
+    timeout: Duration,
+    mut f: impl FnMut() -> Result<G, TryLockError<G>>,
+) -> Result<G, LockError> {
+    let start = Instant::now();
+    let mut backoff = Duration::from_micros(1);
+    loop {
+        match try_lock(f()) {
+            Err(LockError::WouldBlock) => {}
+            r => return r,
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(LockError::TimedOut);
+        }
+        if backoff < Duration::from_micros(64) {
+            thread::yield_now();
+        } else {
+            thread::sleep(backoff.min(timeout - elapsed));
+        }
+        backoff = (backoff * 2).min(Duration::from_millis(1));
+    }
+}
+
+/// A thread-safe pointer backed by `Arc<RwLock<T>>`, allowing many readers or a single writer at a time.
+///
+/// Supports the same operators as [`Imp`](crate::Imp), each acquiring the lock for the duration of the operation.
+pub struct SyncImp<T: ?Sized> {
+    v: Arc<RwLock<T>>,
+}
+
+impl<T> SyncImp<T> {
+    /// Returns a thread-safe pointer to the data
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The value to be pointed to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use interior_mutability_pointer::SyncImp;
+    /// let mut p = SyncImp::new(0);
+    /// let p2 = p.clone();
+    /// std::thread::spawn(move || p += 5).join().unwrap();
+    /// assert_eq!(*p2.read(), 5);
+    /// ```
+    pub fn new(t: T) -> Self {
+        Self {
+            v: Arc::new(RwLock::new(t)),
+        }
+    }
+}
+
+impl<T: ?Sized> SyncImp<T> {
+    /// Locks the value for reading, blocking the current thread until it can be acquired.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.v.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the value for writing, blocking the current thread until it can be acquired.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.v.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Attempts to lock the value for reading without blocking.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::{LockError, SyncImp};
+    /// let p = SyncImp::new(5);
+    /// let w = p.write();
+    /// assert_eq!(p.try_read().unwrap_err(), LockError::WouldBlock);
+    /// drop(w);
+    /// assert_eq!(*p.try_read().unwrap(), 5);
+    /// ```
+    pub fn try_read(&self) -> Result<RwLockReadGuard<'_, T>, LockError> {
+        try_lock(self.v.try_read())
+    }
+
+    /// Attempts to lock the value for writing without blocking.
+    pub fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>, LockError> {
+        try_lock(self.v.try_write())
+    }
+
+    /// Attempts to lock the value for reading, giving up once `timeout` has passed.
+    pub fn read_timeout(&self, timeout: Duration) -> Result<RwLockReadGuard<'_, T>, LockError> {
+        lock_timeout(timeout, || self.v.try_read())
+    }
+
+    /// Attempts to lock the value for writing, giving up once `timeout` has passed.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use interior_mutability_pointer::{LockError, SyncImp};
+    /// let p = SyncImp::new(5);
+    /// let r = p.read();
+    /// assert_eq!(p.write_timeout(Duration::from_millis(1)).unwrap_err(), LockError::TimedOut);
+    /// ```
+    pub fn write_timeout(&self, timeout: Duration) -> Result<RwLockWriteGuard<'_, T>, LockError> {
+        lock_timeout(timeout, || self.v.try_write())
+    }
+
+    /// Returns true if two pointers are equal
+    ///
+    /// # Arguments
+    /// * `this` - A pointer to compare
+    /// * `other` - The other pointer to compare to
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.v, &other.v)
+    }
+
+    pub(crate) fn as_ptr(this: &Self) -> *const () {
+        Arc::as_ptr(&this.v) as *const ()
+    }
+}
+
+/// A thread-safe pointer backed by `Arc<Mutex<T>>`, allowing a single accessor at a time.
+///
+/// Unlike [`SyncImp`], only requires `T: Send` to be shared across threads.
+/// Supports the same operators as [`Imp`](crate::Imp), each acquiring the lock for the duration of the operation.
+pub struct MutexImp<T: ?Sized> {
+    v: Arc<Mutex<T>>,
+}
+
+impl<T> MutexImp<T> {
+    /// Returns a thread-safe pointer to the data
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The value to be pointed to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use interior_mutability_pointer::MutexImp;
+    /// let p = MutexImp::new(String::new());
+    /// let p2 = p.clone();
+    /// std::thread::spawn(move || p2.lock().push_str("yo")).join().unwrap();
+    /// assert_eq!(*p.lock(), "yo");
+    /// ```
+    pub fn new(t: T) -> Self {
+        Self {
+            v: Arc::new(Mutex::new(t)),
+        }
+    }
+}
+
+impl<T: ?Sized> MutexImp<T> {
+    /// Locks the value, blocking the current thread until it can be acquired.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.v.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Attempts to lock the value without blocking.
+    pub fn try_lock(&self) -> Result<MutexGuard<'_, T>, LockError> {
+        try_lock(self.v.try_lock())
+    }
+
+    /// Attempts to lock the value, giving up once `timeout` has passed.
+    pub fn lock_timeout(&self, timeout: Duration) -> Result<MutexGuard<'_, T>, LockError> {
+        lock_timeout(timeout, || self.v.try_lock())
+    }
+
+    /// Returns true if two pointers are equal
+    ///
+    /// # Arguments
+    /// * `this` - A pointer to compare
+    /// * `other` - The other pointer to compare to
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.v, &other.v)
+    }
+
+    pub(crate) fn as_ptr(this: &Self) -> *const () {
+        Arc::as_ptr(&this.v) as *const ()
+    }
+}
+
+/*
+    Implements cloning the pointers.
+*/
+mod clone_impl {
+    use super::{MutexImp, SyncImp};
+
+    impl<T: ?Sized> Clone for SyncImp<T> {
+        fn clone(&self) -> Self {
+            Self { v: self.v.clone() }
+        }
+    }
+
+    impl<T: ?Sized> Clone for MutexImp<T> {
+        fn clone(&self) -> Self {
+            Self { v: self.v.clone() }
+        }
+    }
+}
+
+/*
+    Allows coercing into pointers to trait objects, the same way as Imp.
+*/
+#[cfg(feature = "nightly")]
+mod unsize_impl {
+    use std::{
+        marker::Unsize,
+        ops::{CoerceUnsized, DispatchFromDyn},
+    };
+
+    use super::{MutexImp, SyncImp};
+
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<SyncImp<U>> for SyncImp<T> {}
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<SyncImp<U>> for SyncImp<T> {}
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<MutexImp<U>> for MutexImp<T> {}
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<MutexImp<U>> for MutexImp<T> {}
+}
@@ -26,6 +26,149 @@ mod compile_failures {
     }
 }
 
+#[cfg(feature = "checked")]
+mod checked {
+    use std::ops::{Deref, DerefMut};
+
+    use crate::{GuardedImp, HistoryImp, Imp, MappedImp, ObservableImp};
+
+    #[test]
+    #[should_panic(expected = "shared access at src/tests.rs")]
+    fn deref_while_written() {
+        let p1 = unsafe { Imp::new(vec![1]) };
+        let p2 = p1.clone();
+        let _w = Imp::write(&p1);
+        let _ = p2.len();
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps shared access at src/tests.rs")]
+    fn deref_mut_while_read() {
+        let p1 = unsafe { Imp::new(vec![1]) };
+        let mut p2 = p1.clone();
+        let _r = Imp::read(&p1);
+        p2.push(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps exclusive access at src/tests.rs")]
+    fn read_while_written() {
+        let p = GuardedImp::new(1);
+        let _w = p.write();
+        let _r = p.read();
+    }
+
+    #[test]
+    fn try_write_while_read() {
+        let p = unsafe { Imp::new(1) };
+        let _r = Imp::read(&p);
+        assert!(Imp::try_write(&p).is_err());
+        assert_eq!(*p, 1);
+    }
+
+    #[test]
+    fn released_on_drop() {
+        let mut p1 = unsafe { Imp::new(1) };
+        let p2 = p1.clone();
+        drop(Imp::write(&p2));
+        *p1.deref_mut() += 1;
+        p1 += p2.clone();
+        assert_eq!(*p2.deref(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "shared access at src/tests.rs")]
+    fn mapped_deref_while_root_written() {
+        let p = unsafe { Imp::new((1, 2)) };
+        let a = Imp::map(&p, |t| &t.0, |t| &mut t.0);
+        let _w = Imp::write(&p);
+        let _ = *a.deref();
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps shared access at src/tests.rs")]
+    fn root_write_while_mapped_read() {
+        let p = unsafe { Imp::new((1, 2)) };
+        let a = Imp::map(&p, |t| &t.0, |t| &mut t.0);
+        let _r = MappedImp::read(&a);
+        let _w = Imp::write(&p);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps exclusive access at src/tests.rs")]
+    fn deref_mut_kept_across_clone_deref() {
+        let mut p1 = unsafe { Imp::new(1) };
+        let p2 = p1.clone();
+        let r = p1.deref_mut();
+        let s = p2.deref();
+        *r = 2;
+        assert_eq!(*s, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "through another pointer")]
+    fn deref_mut_from_two_clones() {
+        let mut p1 = unsafe { Imp::new(vec![1]) };
+        let mut p2 = p1.clone();
+        let r = p1.deref_mut();
+        p2.push(2);
+        r.push(3);
+    }
+
+    #[test]
+    fn deref_mut_ended() {
+        let mut p1 = unsafe { Imp::new(vec![1]) };
+        let p2 = p1.clone();
+        p1.push(2);
+        // Using p1 again means the reference from its DerefMut is gone.
+        assert_eq!(p1.len(), 2);
+        assert_eq!(p2.len(), 2);
+        p1.push(3);
+        // So does taking a guard.
+        assert_eq!(Imp::read(&p2).len(), 3);
+        assert_eq!(p2.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "through another pointer")]
+    fn mapped_deref_mut_across_root_deref() {
+        let p = unsafe { Imp::new((1, 2)) };
+        let mut a = Imp::map(&p, |t| &t.0, |t| &mut t.0);
+        let r = a.deref_mut();
+        assert_eq!(p.1, 2);
+        *r = 3;
+    }
+
+    #[test]
+    #[should_panic(expected = "through another pointer")]
+    fn observable_deref_mut_across_clone_deref() {
+        let mut p1 = unsafe { ObservableImp::new(1) };
+        let p2 = p1.clone();
+        let r = p1.deref_mut();
+        assert_eq!(*p2, 1);
+        *r = 2;
+    }
+
+    #[test]
+    #[should_panic(expected = "through another pointer")]
+    fn history_deref_mut_across_clone_deref() {
+        let mut p1 = unsafe { HistoryImp::new(1) };
+        let p2 = p1.clone();
+        let r = p1.deref_mut();
+        assert_eq!(*p2, 1);
+        *r = 2;
+    }
+
+    #[test]
+    fn separate_allocations() {
+        let mut p1 = unsafe { Imp::new(1) };
+        let p2 = unsafe { Imp::new(2) };
+        let _w = Imp::write(&p2);
+        *p1 += 1;
+        assert_eq!(*p1, 2);
+    }
+}
+
 // Test equality
 mod ptr_eq {
     use crate::Imp;
@@ -45,8 +188,983 @@ mod ptr_eq {
     }
 }
 
+mod guard {
+    use crate::{GuardedImp, Imp};
+
+    #[test]
+    fn read_write() {
+        let p1 = unsafe { Imp::new(vec![1, 2]) };
+        let p2 = p1.clone();
+        Imp::write(&p1).push(3);
+        assert_eq!(*Imp::read(&p2), [1, 2, 3]);
+    }
+
+    #[test]
+    fn try_read_while_written() {
+        let p = unsafe { Imp::new(5) };
+        let w = Imp::write(&p);
+        assert!(Imp::try_read(&p).is_err());
+        assert!(Imp::try_write(&p).is_err());
+        drop(w);
+        assert!(Imp::try_read(&p).is_ok());
+    }
+
+    #[test]
+    fn try_write_while_read() {
+        let p = GuardedImp::new(5);
+        let r1 = p.read();
+        let r2 = p.try_read().unwrap();
+        assert!(p.try_write().is_err());
+        assert_eq!(*r1, *r2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn write_while_read() {
+        let p = GuardedImp::new(5);
+        let _r = p.read();
+        let _w = p.write();
+    }
+
+    #[test]
+    fn guarded_shared() {
+        let p1 = GuardedImp::new(String::new());
+        let p2 = p1.clone();
+        p1.write().push_str("yo");
+        assert_eq!(*p2.read(), "yo");
+        assert!(GuardedImp::ptr_eq(&p1, &p2));
+    }
+
+    #[test]
+    fn guarded_operators() {
+        let mut p1 = GuardedImp::new(2);
+        let p2 = p1.clone();
+        p1 += 3;
+        p1 *= p2.clone();
+        assert_eq!(p2, 25);
+        assert_eq!(p1.clone() - 5, 20);
+        assert!(p1 > 20);
+        assert_eq!(format!("{:?}", p2), "25");
+    }
+
+    #[test]
+    fn guarded_from_imp() {
+        let p1 = unsafe { Imp::new(1) };
+        let p2 = GuardedImp::from(p1.clone());
+        *p2.write() = 2;
+        assert_eq!(p1, 2);
+    }
+}
+
+mod weak {
+    use crate::{Imp, WeakImp};
+
+    struct Node {
+        parent: WeakImp<Node>,
+        children: Vec<Imp<Node>>,
+        value: i32,
+    }
+
+    #[test]
+    fn upgrade() {
+        let p = unsafe { Imp::new(5) };
+        let w = Imp::downgrade(&p);
+        let mut p2 = w.upgrade().unwrap();
+        *p2 += 1;
+        assert_eq!(p, 6);
+    }
+
+    #[test]
+    fn upgrade_dropped() {
+        let p = unsafe { Imp::new(5) };
+        let w = Imp::downgrade(&p);
+        drop(p);
+        assert!(w.upgrade().is_none());
+        assert!(WeakImp::<i32>::new().upgrade().is_none());
+    }
+
+    #[test]
+    fn counts() {
+        let p = unsafe { Imp::new(5) };
+        let w = Imp::downgrade(&p);
+        let _w2 = w.clone();
+        let _p2 = p.clone();
+        assert_eq!(w.strong_count(), 2);
+        assert_eq!(w.weak_count(), 2);
+        assert_eq!(Imp::weak_count(&p), 2);
+    }
+
+    #[test]
+    fn ptr_eq() {
+        let p1 = unsafe { Imp::new(5) };
+        let p2 = unsafe { Imp::new(5) };
+        assert!(Imp::downgrade(&p1).ptr_eq(&Imp::downgrade(&p1)));
+        assert!(!Imp::downgrade(&p1).ptr_eq(&Imp::downgrade(&p2)));
+    }
+
+    #[test]
+    fn parent_pointers() {
+        let mut root = unsafe {
+            Imp::new(Node {
+                parent: WeakImp::new(),
+                children: vec![],
+                value: 1,
+            })
+        };
+        let child = unsafe {
+            Imp::new(Node {
+                parent: Imp::downgrade(&root),
+                children: vec![],
+                value: 2,
+            })
+        };
+        root.children.push(child.clone());
+        assert_eq!(child.parent.upgrade().unwrap().value, 1);
+        assert_eq!(root.children[0].value, 2);
+
+        let weak_root = Imp::downgrade(&root);
+        drop(root);
+        assert!(weak_root.upgrade().is_none());
+        assert!(child.parent.upgrade().is_none());
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn unsized_coercion() {
+        let p = unsafe { Imp::new([1, 2, 3]) };
+        let w = Imp::downgrade(&p);
+        let w: WeakImp<[i32]> = w;
+        assert_eq!(w.upgrade().unwrap().len(), 3);
+    }
+}
+
+mod cyclic {
+    use crate::{Imp, WeakImp};
+
+    struct Node {
+        me: WeakImp<Node>,
+        children: Vec<Imp<Node>>,
+    }
+
+    #[test]
+    fn self_pointer() {
+        let p = unsafe {
+            Imp::new_cyclic(|me| {
+                assert!(me.upgrade().is_none());
+                Node {
+                    me: me.clone(),
+                    children: vec![],
+                }
+            })
+        };
+        assert!(Imp::ptr_eq(&p.me.upgrade().unwrap(), &p));
+        assert_eq!(Imp::weak_count(&p), 1);
+    }
+
+    #[test]
+    fn container_pointer() {
+        let mut root = unsafe {
+            Imp::new_cyclic(|me: &WeakImp<Node>| Node {
+                me: me.clone(),
+                children: vec![],
+            })
+        };
+        let child = unsafe {
+            Imp::new_cyclic(|me| Node {
+                me: me.clone(),
+                children: vec![],
+            })
+        };
+        root.children.push(child.clone());
+        assert!(Imp::ptr_eq(&root.children[0].me.upgrade().unwrap(), &child));
+        let weak = Imp::downgrade(&root);
+        drop(root);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn uninit() {
+        let p = unsafe { Imp::<Vec<i32>>::new_uninit() };
+        let mut p2 = p.clone();
+        p2.write(vec![1, 2]);
+        let p = unsafe { Imp::assume_init(p) }.unwrap_err();
+        drop(p2);
+        let w = Imp::downgrade(&p);
+        let p = unsafe { Imp::assume_init(p) }.unwrap_err();
+        drop(w);
+        let mut p = unsafe { Imp::assume_init(p) }.ok().unwrap();
+        p.push(3);
+        assert_eq!(p, vec![1, 2, 3]);
+    }
+}
+
+mod mapped {
+    use crate::{Imp, MappedImp};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Player {
+        name: String,
+        hp: i32,
+        items: Vec<String>,
+    }
+
+    fn player() -> Imp<Player> {
+        unsafe {
+            Imp::new(Player {
+                name: "yo".into(),
+                hp: 100,
+                items: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn deref() {
+        let p = player();
+        let mut name = Imp::map(&p, |p| &p.name, |p| &mut p.name);
+        name.push_str("oo");
+        assert_eq!(Imp::read(&p).name, "yooo");
+        assert_eq!(name.len(), 4);
+    }
+
+    #[test]
+    fn operators() {
+        let p = player();
+        let mut hp = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        hp -= 10;
+        hp *= 2;
+        assert_eq!(p.hp, 180);
+        assert_eq!(hp, 180);
+        assert!(hp > 100);
+        assert_eq!(hp.clone() + 1, 181);
+        assert_eq!(-&hp, -180);
+        assert_eq!(format!("{} {:?}", hp, hp), "180 180");
+    }
+
+    #[test]
+    fn keeps_root_alive() {
+        let p = player();
+        let items = Imp::map(&p, |p| &p.items, |p| &mut p.items);
+        drop(p);
+        MappedImp::write(&items).push("sword".into());
+        assert_eq!(*MappedImp::read(&items), ["sword"]);
+    }
+
+    #[test]
+    fn clones_share_root() {
+        let p = player();
+        let mut hp1 = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        let hp2 = hp1.clone();
+        let hp3 = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        hp1 += 1;
+        assert_eq!(hp2, 101);
+        assert!(MappedImp::ptr_eq(&hp1, &hp2));
+        assert!(MappedImp::ptr_eq(&hp1, &hp3));
+        assert!(!MappedImp::ptr_eq(
+            &hp1,
+            &Imp::map(&player(), |p| &p.hp, |p| &mut p.hp)
+        ));
+    }
+
+    #[test]
+    fn follows_reallocation() {
+        let mut v = unsafe { Imp::new(vec![1]) };
+        let mut first = Imp::map(&v, |v| &v[0], |v| &mut v[0]);
+        v.extend(0..100);
+        first += 1;
+        assert_eq!(v[0], 2);
+    }
+
+    #[test]
+    fn compose() {
+        let p = player();
+        let items = Imp::map(&p, |p| &p.items, |p| &mut p.items);
+        Imp::write(&p).items.push("shield".into());
+        let mut first = MappedImp::map(&items, |i| &i[0], |i| &mut i[0]);
+        first.push('s');
+        assert_eq!(Imp::read(&p).items, ["shields"]);
+    }
+
+    #[test]
+    fn borrow_flag() {
+        let p = player();
+        let hp = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        let name = Imp::map(&p, |p| &p.name, |p| &mut p.name);
+        let r = MappedImp::read(&hp);
+        assert!(MappedImp::try_read(&name).is_ok());
+        assert!(MappedImp::try_write(&name).is_err());
+        assert!(Imp::try_write(&p).is_err());
+        drop(r);
+        let w = MappedImp::write(&name);
+        assert!(Imp::try_read(&p).is_err());
+        drop(w);
+        assert!(MappedImp::try_write(&hp).is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn read_while_written() {
+        let p = player();
+        let hp = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        let _w = Imp::write(&p);
+        let _r = MappedImp::read(&hp);
+    }
+
+    #[test]
+    fn shared_access_while_read() {
+        let p = player();
+        let hp = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        let _r = Imp::read(&p);
+        let _r2 = MappedImp::read(&hp);
+        assert!(MappedImp::ptr_eq(&hp, &hp.clone()));
+        assert_eq!(&hp + &hp, 200);
+        assert!(hp == hp.clone());
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn ptr_eq_while_written() {
+        let p = player();
+        let hp = Imp::map(&p, |p| &p.hp, |p| &mut p.hp);
+        let _w = Imp::write(&p);
+        MappedImp::ptr_eq(&hp, &hp);
+    }
+
+    #[test]
+    fn with_pair_fields() {
+        let p = unsafe { Imp::new((1, 2)) };
+        let mut a = Imp::map(&p, |t| &t.0, |t| &mut t.0);
+        let b = Imp::map(&p, |t| &t.1, |t| &mut t.1);
+        assert!(a < b);
+        assert_eq!(&a + &b, 3);
+        a += b.clone();
+        a += &b;
+        assert_eq!(*p, (5, 2));
+    }
+}
+
+mod observable {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{ObservableImp, Subscription};
+
+    fn record<T: Clone + 'static>(p: &ObservableImp<T>) -> (Rc<RefCell<Vec<T>>>, Subscription) {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let s = seen.clone();
+        let sub = ObservableImp::subscribe(p, move |v: &T| s.borrow_mut().push(v.clone()));
+        (seen, sub)
+    }
+
+    #[test]
+    fn operators() {
+        let mut p = unsafe { ObservableImp::new(1) };
+        let (seen, _sub) = record(&p);
+        p += 1;
+        p *= 3;
+        p -= p.clone();
+        assert_eq!(*seen.borrow(), [2, 6, 0]);
+        assert_eq!(ObservableImp::version(&p), 3);
+    }
+
+    #[test]
+    fn guard() {
+        let p = unsafe { ObservableImp::new(vec![1]) };
+        let (seen, _sub) = record(&p);
+        {
+            let mut w = ObservableImp::write(&p);
+            w.push(2);
+            w.push(3);
+            assert!(seen.borrow().is_empty());
+        }
+        assert_eq!(*seen.borrow(), [vec![1, 2, 3]]);
+        p.clone().extend([4]);
+        assert_eq!(seen.borrow().len(), 2);
+    }
+
+    #[test]
+    fn deref_mut_notifies_on_next_access() {
+        let mut p = unsafe { ObservableImp::new(vec![1]) };
+        let p2 = p.clone();
+        let (seen, _sub) = record(&p);
+        p.push(2);
+        assert_eq!(ObservableImp::version(&p), 1);
+        assert!(seen.borrow().is_empty());
+        assert_eq!(ObservableImp::read(&p2).len(), 2);
+        assert_eq!(*seen.borrow(), [vec![1, 2]]);
+        p[0] = 5;
+        ObservableImp::flush(&p);
+        assert_eq!(seen.borrow()[1], [5, 2]);
+        ObservableImp::flush(&p);
+        assert_eq!(seen.borrow().len(), 2);
+        assert_eq!(ObservableImp::version(&p), 2);
+    }
+
+    #[test]
+    fn batch() {
+        let mut p = unsafe { ObservableImp::new(0) };
+        let p2 = p.clone();
+        let (seen, _sub) = record(&p);
+        ObservableImp::batch(&p2, || {
+            p += 1;
+            ObservableImp::batch(&p2, || *ObservableImp::write(&p2) += 1);
+            *p += 1;
+            assert_eq!(*ObservableImp::read(&p2), 3);
+            assert!(seen.borrow().is_empty());
+        });
+        assert_eq!(*seen.borrow(), [3]);
+        ObservableImp::batch(&p2, || ());
+        assert_eq!(seen.borrow().len(), 1);
+    }
+
+    #[test]
+    fn batch_ends_on_panic() {
+        let mut p = unsafe { ObservableImp::new(0) };
+        let p2 = p.clone();
+        let (seen, _sub) = record(&p);
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ObservableImp::batch(&p2, || {
+                p += 1;
+                panic!();
+            })
+        }));
+        assert!(r.is_err());
+        ObservableImp::flush(&p2);
+        assert_eq!(*seen.borrow(), [1]);
+    }
+
+    #[test]
+    fn unsubscribe() {
+        let mut p = unsafe { ObservableImp::new(0) };
+        let (seen, sub) = record(&p);
+        let (kept, detached) = record(&p);
+        detached.detach();
+        p += 1;
+        drop(sub);
+        p += 1;
+        assert_eq!(*seen.borrow(), [1]);
+        assert_eq!(*kept.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn subscribe_in_callback() {
+        let mut p = unsafe { ObservableImp::new(0) };
+        let p2 = p.clone();
+        let subs = Rc::new(RefCell::new(vec![]));
+        let s = subs.clone();
+        ObservableImp::subscribe(&p, move |_| {
+            s.borrow_mut().push(ObservableImp::subscribe(&p2, |_| ()))
+        })
+        .detach();
+        p += 1;
+        p += 1;
+        assert_eq!(subs.borrow().len(), 2);
+    }
+
+    #[test]
+    fn polling() {
+        let mut p = unsafe { ObservableImp::new(String::new()) };
+        let seen = ObservableImp::version(&p);
+        assert_eq!(p.len(), 0);
+        assert_eq!(ObservableImp::version(&p), seen);
+        p.push_str("yo");
+        assert_ne!(ObservableImp::version(&p), seen);
+        assert_eq!(format!("{:?}", p), "\"yo\"");
+    }
+}
+
+mod history {
+    use crate::HistoryImp;
+
+    #[test]
+    fn undo_redo_shared() {
+        let mut p = unsafe { HistoryImp::new(vec![1]) };
+        let q = p.clone();
+        p.push(2);
+        p[0] = 5;
+        assert!(HistoryImp::undo(&q));
+        assert_eq!(q, vec![1, 2]);
+        assert!(HistoryImp::undo(&q));
+        assert_eq!(p, vec![1]);
+        assert!(!HistoryImp::undo(&p));
+        assert!(HistoryImp::redo(&p));
+        assert_eq!(*HistoryImp::read(&q), [1, 2]);
+        assert_eq!((HistoryImp::undo_len(&p), HistoryImp::redo_len(&p)), (1, 1));
+    }
+
+    #[test]
+    fn new_change_drops_redo() {
+        let mut p = unsafe { HistoryImp::new(1) };
+        p += 1;
+        HistoryImp::undo(&p);
+        *HistoryImp::write(&p) = 10;
+        assert!(!HistoryImp::redo(&p));
+        assert!(HistoryImp::undo(&p));
+        assert_eq!(p, 1);
+    }
+
+    #[test]
+    fn manual_checkpoints() {
+        let mut p = unsafe { HistoryImp::new(String::new()) };
+        HistoryImp::set_auto(&p, false);
+        p.push('a');
+        HistoryImp::checkpoint(&p);
+        p.push('b');
+        p.push('c');
+        assert_eq!(HistoryImp::undo_len(&p), 1);
+        HistoryImp::undo(&p);
+        assert_eq!(*p, "a");
+        HistoryImp::redo(&p);
+        assert_eq!(*p, "abc");
+    }
+
+    #[test]
+    fn depth_limit() {
+        let mut p = unsafe { HistoryImp::new(0) };
+        HistoryImp::limit_depth(&p, 2);
+        for _ in 0..5 {
+            p += 1;
+        }
+        assert_eq!(HistoryImp::undo_len(&p), 2);
+        while HistoryImp::undo(&p) {}
+        assert_eq!(p, 3);
+        HistoryImp::limit_depth(&p, 0);
+        assert_eq!(HistoryImp::redo_len(&p), 2);
+    }
+
+    #[test]
+    fn size_limit() {
+        let p = unsafe { HistoryImp::new(vec![0u8; 10]) };
+        HistoryImp::limit_size(&p, 25, Vec::len);
+        for _ in 0..3 {
+            HistoryImp::write(&p).push(0);
+        }
+        // Checkpoints of 10, 11 and 12 bytes were recorded, the oldest did not fit.
+        assert_eq!(HistoryImp::undo_len(&p), 2);
+        HistoryImp::limit_size(&p, 0, Vec::len);
+        assert_eq!(HistoryImp::undo_len(&p), 0);
+    }
+
+    #[test]
+    fn redo_over_size_limit() {
+        let p = unsafe { HistoryImp::new(vec![0u8; 10]) };
+        HistoryImp::limit_size(&p, 10, Vec::len);
+        HistoryImp::write(&p).push(1);
+        assert!(HistoryImp::undo(&p));
+        // The 11 byte checkpoint to redo to does not fit, but is kept.
+        assert_eq!(HistoryImp::redo_len(&p), 1);
+        assert!(HistoryImp::redo(&p));
+        assert_eq!(HistoryImp::read(&p).len(), 11);
+        assert!(HistoryImp::undo(&p));
+        assert_eq!(HistoryImp::read(&p).len(), 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn undo_while_borrowed() {
+        let p = unsafe { HistoryImp::new(1) };
+        HistoryImp::checkpoint(&p);
+        let _r = HistoryImp::read(&p);
+        HistoryImp::undo(&p);
+    }
+}
+
+mod transaction {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use crate::{Imp, Transaction};
+
+    #[test]
+    fn commit_keeps_changes() {
+        let a = unsafe { Imp::new(1) };
+        let mut tx = Transaction::new();
+        *tx.write(&a) += 1;
+        tx.commit();
+        assert_eq!(a, 2);
+    }
+
+    #[test]
+    fn drop_restores_all() {
+        let a = unsafe { Imp::new(1) };
+        let b = unsafe { Imp::new(String::from("yo")) };
+        let c = unsafe { Imp::new(vec![1, 2]) };
+        {
+            let mut tx = Transaction::new();
+            *tx.write(&a) = 5;
+            tx.write(&b).push('!');
+            tx.write(&c).clear();
+            // Only the first write is recorded.
+            *tx.write(&a) = 6;
+        }
+        assert_eq!(a, 1);
+        assert_eq!(*b, "yo");
+        assert_eq!(c, vec![1, 2]);
+    }
+
+    #[test]
+    fn record_then_deref_mut() {
+        let mut a = unsafe { Imp::new(vec![1]) };
+        let mut tx = Transaction::new();
+        tx.record(&a);
+        a.push(2);
+        a.extend([3]);
+        tx.rollback();
+        assert_eq!(a, vec![1]);
+    }
+
+    #[test]
+    fn run() {
+        let a = unsafe { Imp::new(1) };
+        let r: Result<i32, ()> = Transaction::run(|tx| {
+            *tx.write(&a) += 1;
+            Ok(*a)
+        });
+        assert_eq!(r, Ok(2));
+        let r: Result<(), i32> = Transaction::run(|tx| {
+            *tx.write(&a) += 1;
+            Err(*a)
+        });
+        assert_eq!(r, Err(3));
+        assert_eq!(a, 2);
+    }
+
+    #[test]
+    fn panic_restores() {
+        let a = unsafe { Imp::new(1) };
+        let r = catch_unwind(AssertUnwindSafe(|| {
+            let mut tx = Transaction::new();
+            *tx.write(&a) = 2;
+            panic!("step failed");
+        }));
+        assert!(r.is_err());
+        assert_eq!(a, 1);
+    }
+
+    #[test]
+    fn restore_while_borrowed() {
+        let a = unsafe { Imp::new(1) };
+        let b = unsafe { Imp::new(1) };
+        let mut tx = Transaction::new();
+        *tx.write(&a) = 2;
+        *tx.write(&b) = 2;
+        let r = Imp::read(&b);
+        let e = catch_unwind(AssertUnwindSafe(|| drop(tx))).unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<String>().unwrap(),
+            "could not undo the changes to 1 of the values, as they are still borrowed"
+        );
+        drop(r);
+        assert_eq!(a, 1);
+        assert_eq!(b, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn restore_while_borrowed_and_unwinding() {
+        let a = unsafe { Imp::new(1) };
+        let b = unsafe { Imp::new(1) };
+        let r = catch_unwind(AssertUnwindSafe(|| {
+            let _guard;
+            let mut tx = Transaction::new();
+            *tx.write(&a) = 2;
+            *tx.write(&b) = 2;
+            _guard = Imp::read(&b);
+            panic!("step failed");
+        }));
+        assert_eq!(
+            *r.unwrap_err().downcast_ref::<&str>().unwrap(),
+            "step failed"
+        );
+        assert_eq!(a, 1);
+        assert_eq!(b, 2);
+    }
+}
+
+#[cfg(feature = "std")]
+mod computed {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{Computed, ObservableImp};
+
+    fn counted<U: 'static>(runs: &Rc<Cell<u32>>, f: impl Fn() -> U + 'static) -> Computed<U> {
+        let runs = runs.clone();
+        Computed::new(move || {
+            runs.set(runs.get() + 1);
+            f()
+        })
+    }
+
+    #[test]
+    fn lazy() {
+        let mut p = unsafe { ObservableImp::new(1) };
+        let runs = Rc::new(Cell::new(0));
+        let q = p.clone();
+        let c = counted(&runs, move || *q * 10);
+        assert_eq!(runs.get(), 0);
+        assert_eq!(Computed::get(&c), 10);
+        assert_eq!(Computed::get(&c), 10);
+        assert_eq!(runs.get(), 1);
+        p += 1;
+        p += 1;
+        assert_eq!(runs.get(), 1);
+        assert_eq!(*Computed::read(&c), 30);
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn diamond() {
+        let mut a = unsafe { ObservableImp::new(1) };
+        let runs = Rc::new(Cell::new(0));
+        let a1 = a.clone();
+        let b = Computed::new(move || *a1 + 1);
+        let a2 = a.clone();
+        let c = Computed::new(move || *a2 * 2);
+        let (b1, c1) = (b.clone(), c.clone());
+        let seen = Rc::new(Cell::new((0, 0)));
+        let s = seen.clone();
+        let d = counted(&runs, move || {
+            let v = (Computed::get(&b1), Computed::get(&c1));
+            s.set(v);
+            v.0 + v.1
+        });
+        assert_eq!(Computed::get(&d), 4);
+        a += 1;
+        assert_eq!(Computed::get(&d), 7);
+        // Never evaluated with one side updated and the other not.
+        assert_eq!(seen.get(), (3, 4));
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn chained() {
+        let mut a = unsafe { ObservableImp::new(1) };
+        let runs = Rc::new(Cell::new(0));
+        let a1 = a.clone();
+        let even = Computed::new(move || *a1 % 2 == 0);
+        let e = even.clone();
+        let label = counted(
+            &runs,
+            move || if Computed::get(&e) { "even" } else { "odd" },
+        );
+        assert_eq!(Computed::get(&label), "odd");
+        a += 2;
+        assert_eq!(Computed::get(&label), "odd");
+        assert_eq!(Computed::version(&even), 2);
+        assert_eq!(runs.get(), 2);
+        // Reading `label` brought `even` up to date, so it is not recomputed again.
+        assert_eq!(Computed::version(&even), 2);
+    }
+
+    #[test]
+    fn dynamic_dependencies() {
+        let flag = unsafe { ObservableImp::new(true) };
+        let mut x = unsafe { ObservableImp::new(1) };
+        let y = unsafe { ObservableImp::new(2) };
+        let runs = Rc::new(Cell::new(0));
+        let (f, x1, y1) = (flag.clone(), x.clone(), y.clone());
+        let c = counted(&runs, move || if *f { *x1 } else { *y1 });
+        assert_eq!(Computed::get(&c), 1);
+        *ObservableImp::write(&flag) = false;
+        assert_eq!(Computed::get(&c), 2);
+        // x is no longer read, so changing it does not recompute.
+        x += 1;
+        assert_eq!(Computed::get(&c), 2);
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn untracked_outside_evaluation() {
+        let p = unsafe { ObservableImp::new(5) };
+        assert_eq!(*ObservableImp::read(&p), 5);
+        let c = Computed::new(|| 1);
+        assert_eq!(format!("{:?}", c), "1");
+    }
+
+    #[test]
+    #[should_panic(expected = "depends on itself")]
+    fn cycle() {
+        let slot: Rc<Cell<Option<Computed<i32>>>> = Rc::new(Cell::new(None));
+        let s = slot.clone();
+        let c = Computed::new(move || {
+            let c = s.take().unwrap();
+            Computed::get(&c) + 1
+        });
+        slot.set(Some(c.clone()));
+        Computed::get(&c);
+    }
+}
+
+mod ownership {
+    use crate::Imp;
+
+    #[derive(Debug, PartialEq)]
+    struct NonCloneable(i32);
+
+    #[test]
+    fn try_unwrap() {
+        let p = unsafe { Imp::new(NonCloneable(1)) };
+        assert_eq!(Imp::try_unwrap(p).unwrap(), NonCloneable(1));
+    }
+
+    #[test]
+    fn try_unwrap_shared() {
+        let p1 = unsafe { Imp::new(NonCloneable(1)) };
+        let p2 = p1.clone();
+        let p1 = Imp::try_unwrap(p1).unwrap_err();
+        assert!(Imp::ptr_eq(&p1, &p2));
+        drop(p2);
+        assert_eq!(Imp::try_unwrap(p1).unwrap(), NonCloneable(1));
+    }
+
+    #[test]
+    fn into_inner() {
+        let p1 = unsafe { Imp::new(NonCloneable(1)) };
+        let p2 = p1.clone();
+        assert!(Imp::into_inner(p1).is_none());
+        assert_eq!(Imp::into_inner(p2), Some(NonCloneable(1)));
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut p = unsafe { Imp::new(1) };
+        *Imp::get_mut(&mut p).unwrap() = 2;
+        assert_eq!(p, 2);
+
+        let w = Imp::downgrade(&p);
+        assert!(!Imp::is_unique(&p));
+        assert!(Imp::get_mut(&mut p).is_none());
+        drop(w);
+        assert!(Imp::is_unique(&p));
+    }
+
+    #[test]
+    fn strong_count() {
+        let p1 = unsafe { Imp::new(1) };
+        assert_eq!(Imp::strong_count(&p1), 1);
+        let p2 = p1.clone();
+        assert_eq!(Imp::strong_count(&p1), 2);
+        drop(p2);
+        assert_eq!(Imp::strong_count(&p1), 1);
+    }
+
+    #[test]
+    fn make_mut() {
+        let mut p1 = unsafe { Imp::new(vec![1]) };
+        Imp::make_mut(&mut p1).push(2);
+        let p2 = p1.clone();
+        Imp::make_mut(&mut p1).push(3);
+        assert_eq!(p1, vec![1, 2, 3]);
+        assert_eq!(p2, vec![1, 2]);
+        assert!(!Imp::ptr_eq(&p1, &p2));
+    }
+}
+
+#[cfg(feature = "std")]
+mod sync {
+    use std::{thread, time::Duration};
+
+    use crate::{LockError, MutexImp, SyncImp};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        assert_send_sync::<SyncImp<i32>>();
+        assert_send_sync::<MutexImp<std::cell::Cell<i32>>>();
+    }
+
+    #[test]
+    fn threads_add_assign() {
+        let p = SyncImp::new(0);
+        let handles = (0..4)
+            .map(|_| {
+                let mut p = p.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        p += 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        assert_eq!(p, 400);
+    }
+
+    #[test]
+    fn mutex_threads() {
+        let p = MutexImp::new(vec![]);
+        let handles = (0..4)
+            .map(|i| {
+                let p = p.clone();
+                thread::spawn(move || p.lock().push(i))
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        assert_eq!(p.lock().len(), 4);
+    }
+
+    #[test]
+    fn operators() {
+        let p1 = MutexImp::new(6);
+        let p2 = p1.clone();
+        assert_eq!(p1.clone() * p2.clone(), 36);
+        assert_eq!(p1 - 1, 5);
+        assert!(SyncImp::new(1.0) < SyncImp::new(2.0));
+    }
+
+    #[test]
+    fn compare_same_lock() {
+        let p1 = MutexImp::new(6);
+        let p2 = p1.clone();
+        assert_eq!(p1, p2);
+        assert!(p1 <= p2);
+    }
+
+    #[test]
+    fn try_lock() {
+        let p = MutexImp::new(1);
+        let g = p.lock();
+        assert_eq!(p.try_lock().unwrap_err(), LockError::WouldBlock);
+        drop(g);
+        assert_eq!(*p.try_lock().unwrap(), 1);
+
+        let p = SyncImp::new(1);
+        let _r = p.read();
+        assert!(p.try_read().is_ok());
+        assert_eq!(p.try_write().unwrap_err(), LockError::WouldBlock);
+    }
+
+    #[test]
+    fn timeout() {
+        let p = SyncImp::new(1);
+        let w = p.write();
+        assert_eq!(
+            p.read_timeout(Duration::from_millis(5)).unwrap_err(),
+            LockError::TimedOut
+        );
+        let p2 = p.clone();
+        let h = thread::spawn(move || *p2.write_timeout(Duration::from_secs(10)).unwrap() += 1);
+        thread::sleep(Duration::from_millis(5));
+        drop(w);
+        h.join().unwrap();
+        assert_eq!(*p.read(), 2);
+    }
+
+    #[test]
+    fn poisoned() {
+        let p = MutexImp::new(1);
+        let p2 = p.clone();
+        let _ = thread::spawn(move || {
+            let _g = p2.lock();
+            panic!();
+        })
+        .join();
+        assert_eq!(*p.lock(), 1);
+    }
+}
+
 // Just ensure this compiles, as it is possible with Rc<RefCell<T>>
 // and should work with Imp<T>
+#[cfg(feature = "nightly")]
 mod dynamic_dispatch {
     use std::{cell::RefCell, ops::Deref, rc::Rc};
 
@@ -110,46 +1228,271 @@ mod dynamic_dispatch {
         let rc = rc.iter().map(|p| p.sound()).collect::<Vec<_>>();
         let imp = imp.iter().map(|p| p.sound()).collect::<Vec<_>>();
 
-        assert!((rc_refcell == rc) && (rc == imp));
+        assert!((rc_refcell == rc) && (rc == imp));
+    }
+
+    #[test]
+    fn test_mut() {
+        let s = Sheep { volume: 10 };
+        let d = Dog { volume: 15 };
+
+        let mut rc_refcell: Vec<Rc<RefCell<dyn Animal>>> =
+            vec![Rc::new(RefCell::new(s)), Rc::new(RefCell::new(d))];
+        let mut imp: Vec<Imp<dyn Animal>> = vec![unsafe { Imp::new(s) }, unsafe { Imp::new(d) }];
+
+        rc_refcell.iter_mut().for_each(|a| {
+            let v = a.borrow().volume();
+            a.borrow_mut().set_volume(v * 2);
+        });
+
+        imp.iter_mut().for_each(|a| {
+            let v = a.volume();
+            a.set_volume(v * 2);
+        });
+
+        let rc_refcell = rc_refcell
+            .iter()
+            .map(|p| p.borrow().volume())
+            .collect::<Vec<_>>();
+        let imp = imp.iter().map(|p| p.volume()).collect::<Vec<_>>();
+
+        assert_eq!(&rc_refcell, &[20, 30]);
+        assert_eq!(&imp, &[20, 30]);
+        assert!(rc_refcell == imp);
+    }
+
+    // Thiss just needs to compile.
+    #[test]
+    fn clone_ref() {
+        let v: Vec<Imp<dyn Animal>> = vec![unsafe { Imp::new(Dog { volume: 10 }) }];
+        v.iter().for_each(|i| {
+            let _ = i.clone();
+        });
+    }
+}
+
+// Same as above, but building the trait objects through imp_dyn! on stable.
+mod dynamic_dispatch_stable {
+    use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+    use crate::{imp_dyn, Imp};
+
+    trait Animal {
+        fn sound(&self) -> &'static str;
+        fn volume(&self) -> i32;
+        fn set_volume(&mut self, v: i32);
+    }
+
+    #[derive(Clone, Copy)]
+    struct Sheep {
+        volume: i32,
+    }
+    impl Animal for Sheep {
+        fn sound(&self) -> &'static str {
+            "baah"
+        }
+
+        fn volume(&self) -> i32 {
+            self.volume
+        }
+
+        fn set_volume(&mut self, v: i32) {
+            self.volume = v;
+        }
+    }
+
+    #[test]
+    fn test_mut() {
+        let s = Sheep { volume: 10 };
+        let mut imp: Vec<Imp<dyn Animal>> = vec![unsafe { imp_dyn!(s) }, unsafe { imp_dyn!(s) }];
+        let p = imp[0].clone();
+
+        imp.iter_mut().for_each(|a| {
+            let v = a.volume();
+            a.set_volume(v * 2);
+        });
+
+        assert_eq!(Imp::read(&p).volume(), 20);
+        assert_eq!(imp[1].sound(), "baah");
+    }
+
+    #[test]
+    fn from_rc() {
+        let rc: Rc<RefCell<dyn Display>> = Rc::new(RefCell::new(5));
+        let p = unsafe { Imp::from_rc(rc) };
+        assert_eq!(p.to_string(), "5");
+    }
+
+    #[test]
+    fn unsized_slice() {
+        let mut p: Imp<[i32]> = unsafe { imp_dyn!([1, 2, 3]) };
+        p[1] = 5;
+        assert_eq!(&*p, &[1, 5, 3]);
+    }
+}
+
+mod downcast {
+    use std::any::Any;
+
+    use crate::{imp_dyn, Imp};
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32, i32);
+
+    #[test]
+    fn component_list() {
+        let v: Vec<Imp<dyn Any>> = vec![unsafe { imp_dyn!(String::from("yo")) }, unsafe {
+            imp_dyn!(Position(1, 2))
+        }];
+        assert!(!Imp::is::<Position>(&v[0]));
+        assert!(Imp::is::<Position>(&v[1]));
+        let mut positions: Vec<Imp<Position>> = v
+            .into_iter()
+            .filter_map(|p| Imp::downcast(p).ok())
+            .collect();
+        positions[0].0 += 1;
+        assert_eq!(*positions[0], Position(2, 2));
+    }
+
+    #[test]
+    fn downcast_shares_value() {
+        let p: Imp<dyn Any> = unsafe { imp_dyn!(5) };
+        let p2 = p.clone();
+        let p = Imp::downcast::<u32>(p).unwrap_err();
+        let mut p = Imp::downcast::<i32>(p).ok().unwrap();
+        p += 1;
+        assert_eq!(*Imp::downcast_ref::<i32>(&p2).unwrap(), 6);
+        assert_eq!(Imp::strong_count(&p), 2);
+    }
+
+    #[test]
+    fn downcast_ref_mut() {
+        let p: Imp<dyn Any> = unsafe { imp_dyn!(vec![1]) };
+        Imp::downcast_mut::<Vec<i32>>(&p).unwrap().push(2);
+        assert!(Imp::downcast_mut::<Vec<u8>>(&p).is_none());
+        assert_eq!(*Imp::downcast_ref::<Vec<i32>>(&p).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn downcast_mut_while_read() {
+        let p: Imp<dyn Any> = unsafe { imp_dyn!(5) };
+        let _r = Imp::downcast_ref::<i32>(&p);
+        let _w = Imp::downcast_mut::<i32>(&p);
+    }
+
+    #[test]
+    #[should_panic]
+    fn downcast_ref_other_type_while_written() {
+        let p: Imp<dyn Any> = unsafe { imp_dyn!(5) };
+        let _w = Imp::downcast_mut::<i32>(&p);
+        Imp::downcast_ref::<u8>(&p);
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn is_while_written() {
+        let p: Imp<dyn Any> = unsafe { imp_dyn!(5) };
+        let _w = Imp::downcast_mut::<i32>(&p);
+        Imp::is::<i32>(&p);
     }
 
     #[test]
-    fn test_mut() {
-        let s = Sheep { volume: 10 };
-        let d = Dog { volume: 15 };
+    fn send() {
+        let p: Imp<dyn Any + Send> = unsafe { imp_dyn!(5u8) };
+        assert!(Imp::is::<u8>(&p));
+        assert_eq!(Imp::downcast::<u8>(p).ok().unwrap(), 5);
+    }
+}
 
-        let mut rc_refcell: Vec<Rc<RefCell<dyn Animal>>> =
-            vec![Rc::new(RefCell::new(s)), Rc::new(RefCell::new(d))];
-        let mut imp: Vec<Imp<dyn Animal>> = vec![unsafe { Imp::new(s) }, unsafe { Imp::new(d) }];
+mod unsized_values {
+    use std::{cell::Cell, rc::Rc};
 
-        rc_refcell.iter_mut().for_each(|a| {
-            let v = a.borrow().volume();
-            a.borrow_mut().set_volume(v * 2);
-        });
+    use crate::{GuardedImp, Imp};
 
-        imp.iter_mut().for_each(|a| {
-            let v = a.volume();
-            a.set_volume(v * 2);
-        });
+    #[test]
+    fn slice_from_vec() {
+        let mut p: Imp<[i32]> = unsafe { Imp::from_vec(vec![1, 2, 3]) };
+        let p2 = p.clone();
+        p[0] = 5;
+        p.sort();
+        assert_eq!(*Imp::read(&p2), [2, 3, 5]);
+        assert_eq!(p.len(), 3);
+    }
 
-        let rc_refcell = rc_refcell
-            .iter()
-            .map(|p| p.borrow().volume())
-            .collect::<Vec<_>>();
-        let imp = imp.iter().map(|p| p.volume()).collect::<Vec<_>>();
+    #[test]
+    fn slice_from_others() {
+        let p: Imp<[u8]> = unsafe { Imp::from_iter([1u8, 2]) };
+        assert_eq!(&*p, [1, 2]);
+        let p: Imp<[String]> = unsafe { Imp::from_iter((1..4).map(|i| i.to_string())) };
+        assert_eq!(&*p, ["1", "2", "3"]);
+        let p: Imp<[()]> = unsafe { Imp::from_vec(vec![(); 3]) };
+        assert_eq!(p.len(), 3);
+        let p: Imp<[u16]> = unsafe { Imp::from_vec(vec![]) };
+        assert!(p.is_empty());
+        let p: Imp<[u16]> = unsafe { Imp::from_iter(None) };
+        assert!(p.is_empty());
+    }
 
-        assert_eq!(&rc_refcell, &[20, 30]);
-        assert_eq!(&imp, &[20, 30]);
-        assert!(rc_refcell == imp);
+    #[test]
+    fn over_aligned() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(align(64))]
+        struct Aligned(u8);
+        let p: Imp<[Aligned]> = unsafe { Imp::from_vec(vec![Aligned(1), Aligned(2)]) };
+        assert_eq!(&*p, [Aligned(1), Aligned(2)]);
+        assert_eq!(p.as_ptr() as *const () as usize % 64, 0);
     }
 
-    // Thiss just needs to compile.
     #[test]
-    fn clone_ref() {
-        let v: Vec<Imp<dyn Animal>> = vec![unsafe { Imp::new(Dog { volume: 10 }) }];
-        v.iter().for_each(|i| {
-            let _ = i.clone();
-        });
+    fn drops_elements_once() {
+        struct Counted(Rc<Cell<i32>>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+        let drops = Rc::new(Cell::new(0));
+        let p: GuardedImp<[Counted]> = (0..5).map(|_| Counted(drops.clone())).collect();
+        let p2 = p.clone();
+        drop(p);
+        assert_eq!(drops.get(), 0);
+        drop(p2);
+        assert_eq!(drops.get(), 5);
+
+        let p: Imp<[Counted]> = unsafe { Imp::from_iter([Counted(drops.clone())]) };
+        assert_eq!(drops.get(), 5);
+        drop(p);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn str() {
+        let mut p: Imp<str> = unsafe { Imp::from_string("yo".into()) };
+        let p2 = p.clone();
+        p.make_ascii_uppercase();
+        assert_eq!(&*Imp::read(&p2), "YO");
+        let p: Imp<str> = unsafe { Imp::from_string(String::from("åäö")) };
+        assert_eq!(p.chars().count(), 3);
+    }
+
+    #[test]
+    fn guarded() {
+        let p: GuardedImp<[i32]> = GuardedImp::from(vec![1, 2]);
+        p.write()[1] = 3;
+        assert_eq!(*p.read(), [1, 3]);
+        let p: GuardedImp<[String]> = GuardedImp::from(&[String::from("yo")][..]);
+        assert_eq!(*p.read(), ["yo"]);
+        let p: GuardedImp<[u8]> = GuardedImp::from(vec![1u8, 2].into_boxed_slice());
+        assert_eq!(*p.read(), [1, 2]);
+        let p: GuardedImp<str> = GuardedImp::from("yo");
+        assert_eq!(&*p.read(), "yo");
+        let p: GuardedImp<str> = GuardedImp::from(String::from("yo").into_boxed_str());
+        assert_eq!(&*p.read(), "yo");
+        let p: GuardedImp<[i32]> = (1..3).collect();
+        assert_eq!(*p.read(), [1, 2]);
+        let p: Imp<[u64]> = unsafe { GuardedImp::into_imp((0..4).map(|i| i * 2).collect()) };
+        assert_eq!(&*p, [0, 2, 4, 6]);
     }
 }
 
@@ -286,6 +1629,31 @@ mod order_box {
 
         assert!(p2 < p1);
     }
+    #[test]
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    fn nan_unordered() {
+        let p1 = unsafe { Imp::new(f64::NAN) };
+        let p2 = unsafe { Imp::new(1.0) };
+
+        assert_eq!(p1.partial_cmp(&p2), None);
+        assert!(!(p1 < p2) && !(p1 > p2) && !(p1 <= p2) && !(p1 >= p2));
+        assert_ne!(p1, p1.clone());
+    }
+    #[test]
+    fn float_ordered() {
+        let p1 = unsafe { Imp::new(-0.5) };
+        let p2 = unsafe { Imp::new(2.5) };
+
+        assert_eq!(p1.partial_cmp(&p2), Some(std::cmp::Ordering::Less));
+    }
+    #[test]
+    fn cmp_delegates() {
+        let p1 = unsafe { Imp::new("b") };
+        let p2 = unsafe { Imp::new("a") };
+
+        assert_eq!(p1.cmp(&p2), std::cmp::Ordering::Greater);
+        assert_eq!(p1.cmp(&p1.clone()), std::cmp::Ordering::Equal);
+    }
 }
 
 mod order_inner {
@@ -334,6 +1702,26 @@ mod order_inner {
 
         assert!(p1 < p2);
     }
+    #[test]
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    fn nan_unordered() {
+        let p1 = unsafe { Imp::new(f64::NAN) };
+
+        assert_eq!(p1.partial_cmp(&1.0), None);
+        assert!(!(p1 < 1.0) && !(p1 >= 1.0));
+        assert!(!(1.0 < p1) && !(1.0 >= p1));
+        assert_ne!(p1, f64::NAN);
+        assert_ne!(f64::NAN, p1);
+    }
+    #[test]
+    fn inner_on_left() {
+        let p1 = unsafe { Imp::new(2.5) };
+
+        assert!(1.0 < p1);
+        assert!(3.0 >= p1);
+        assert_eq!(2.5, p1);
+        assert_eq!(String::from("yo"), unsafe { Imp::new(String::from("yo")) });
+    }
 }
 
 mod index {
@@ -363,6 +1751,32 @@ mod range {
         assert_eq!(t, "oo")
     }
 
+    #[test]
+    fn all_ranges() {
+        let v = unsafe { Imp::new(vec![1, 2, 3, 4, 5]) };
+        assert_eq!(v[2..], [3, 4, 5]);
+        assert_eq!(v[..=1], [1, 2]);
+        assert_eq!(v[..2], [1, 2]);
+        assert_eq!(v[1..=2], [2, 3]);
+        assert_eq!(v[..], [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn keys() {
+        let mut m = unsafe { Imp::new(std::collections::HashMap::from([("key", 1)])) };
+        assert_eq!(m["key"], 1);
+        let b = unsafe { Imp::new(std::collections::BTreeMap::from([(2, "two")])) };
+        assert_eq!(b[&2], "two");
+        *m.get_mut("key").unwrap() += 1;
+        assert_eq!(m["key"], 2);
+    }
+
+    #[test]
+    fn index_str() {
+        let s = unsafe { Imp::new(String::from("yood")) };
+        assert_eq!(&s[1..], "ood");
+    }
+
     #[test]
     fn range_mut() {
         let mut r = unsafe { Imp::new(vec![String::new(); 5]) };
@@ -380,6 +1794,49 @@ mod range {
     }
 }
 
+mod into_iter {
+    use crate::Imp;
+
+    #[test]
+    fn iter_ref() {
+        let v = unsafe { Imp::new(vec![1, 2, 3]) };
+        let mut sum = 0;
+        for x in &v {
+            sum += x;
+        }
+        assert_eq!(sum, 6);
+    }
+
+    #[test]
+    fn iter_mut() {
+        let mut v = unsafe { Imp::new(vec![1, 2, 3]) };
+        let v2 = v.clone();
+        for x in &mut v {
+            *x *= 2;
+        }
+        assert_eq!(v2, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn into_iter_unique() {
+        let v = unsafe { Imp::new(vec![String::from("a"), String::from("b")]) };
+        let w = Imp::downgrade(&v);
+        let items: Vec<String> = v.into_iter().collect();
+        assert_eq!(items, ["a", "b"]);
+        assert!(w.upgrade().is_none());
+    }
+
+    #[test]
+    fn into_iter_shared() {
+        let v = unsafe { Imp::new(vec![1, 2, 3]) };
+        let v2 = v.clone();
+        let items: Vec<i32> = v.into_iter().map(|x| x * 10).collect();
+        assert_eq!(items, [10, 20, 30]);
+        assert_eq!(v2, vec![1, 2, 3]);
+        assert_eq!(Imp::strong_count(&v2), 1);
+    }
+}
+
 mod add {
     use crate::Imp;
 
@@ -420,6 +1877,61 @@ mod add {
         p1 += 2;
         assert_eq!(p1, p2)
     }
+
+    /// A heavy value that is not Copy, supporting addition by value and by reference.
+    #[derive(Clone, PartialEq, Debug)]
+    struct Big(Vec<u32>);
+
+    impl std::ops::Add for Big {
+        type Output = Big;
+
+        fn add(self, other: Big) -> Big {
+            &self + &other
+        }
+    }
+
+    impl std::ops::Add for &Big {
+        type Output = Big;
+
+        fn add(self, other: &Big) -> Big {
+            Big(self.0.iter().zip(&other.0).map(|(a, b)| a + b).collect())
+        }
+    }
+
+    impl std::ops::AddAssign<&Big> for Big {
+        fn add_assign(&mut self, other: &Big) {
+            *self = &*self + other;
+        }
+    }
+
+    #[test]
+    fn add_not_copy() {
+        let p1 = unsafe { Imp::new(Big(vec![1, 2])) };
+        let p2 = unsafe { Imp::new(Big(vec![3, 4])) };
+        let p3 = p1.clone() + p2;
+        assert_eq!(p3, Big(vec![4, 6]));
+        assert_eq!(p1.clone() + Big(vec![1, 1]), Big(vec![2, 3]));
+        assert_eq!(p1, Big(vec![1, 2]));
+    }
+
+    #[test]
+    fn add_by_ref() {
+        let mut p = unsafe { Imp::new(Big(vec![1, 2])) };
+        let b = Big(vec![1, 1]);
+        assert_eq!(p.clone() + &b, Big(vec![2, 3]));
+        p += &b;
+        assert_eq!(p, Big(vec![2, 3]));
+        assert_eq!(b, Big(vec![1, 1]));
+    }
+
+    #[test]
+    fn add_str() {
+        let mut p = unsafe { Imp::new(String::from("yo")) };
+        let p2 = p.clone() + " there";
+        p += "!";
+        assert_eq!(*p2, "yo there");
+        assert_eq!(*p, "yo!");
+    }
 }
 
 mod bitand {
@@ -1091,3 +2603,492 @@ mod sub {
         assert_eq!(p1, p2)
     }
 }
+
+mod mixed_ops {
+    #[cfg(feature = "std")]
+    use std::time::{Instant, SystemTime};
+    use std::{
+        ops::{DivAssign, Mul, Shl},
+        time::Duration,
+    };
+
+    use crate::{GuardedImp, Imp, MixedOps};
+
+    #[test]
+    fn duration_by_u32() {
+        let mut p = unsafe { Imp::new(Duration::from_secs(2)) };
+        assert_eq!(p.clone() * 3, Duration::from_secs(6));
+        assert_eq!(p.clone() / 2, Duration::from_secs(1));
+        p *= 5;
+        p /= 2;
+        assert_eq!(p, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn shift_by_other_integer() {
+        let mut p = unsafe { Imp::new(1u64) };
+        assert_eq!(p.clone() << 3u32, 8);
+        assert_eq!(p.clone() << 3, 8u64);
+        p <<= 4u8;
+        p >>= 1usize;
+        assert_eq!(p, 8);
+        let g = crate::GuardedImp::new(-16i8);
+        assert_eq!(*(g >> 2u128).read(), -4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn instant_and_duration() {
+        let start = Instant::now();
+        let mut p = unsafe { Imp::new(start) };
+        let later = p.clone() + Duration::from_secs(1);
+        assert_eq!(later, start + Duration::from_secs(1));
+        p += Duration::from_secs(2);
+        p -= Duration::from_secs(1);
+        assert_eq!(p, later);
+
+        let elapsed: Imp<Duration> = later - unsafe { Imp::new(start) };
+        assert_eq!(elapsed, Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn system_time_and_duration() {
+        let p = GuardedImp::new(SystemTime::UNIX_EPOCH);
+        let later = p + Duration::from_secs(1);
+        assert_eq!(
+            *later.read(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn output_follows_inner() {
+        #[derive(Clone, Copy)]
+        struct Meters(f64);
+        #[derive(PartialEq, Debug)]
+        struct Area(f64);
+
+        impl std::ops::Mul for Meters {
+            type Output = Area;
+
+            fn mul(self, other: Meters) -> Area {
+                Area(self.0 * other.0)
+            }
+        }
+
+        let p = unsafe { Imp::new(Meters(2.0)) };
+        let area: Imp<Area> = p.clone() * p;
+        assert_eq!(*area, Area(4.0));
+    }
+
+    #[test]
+    fn user_type_by_primitive() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Vec3(f32, f32, f32);
+        impl Mul<f32> for Vec3 {
+            type Output = Vec3;
+            fn mul(self, k: f32) -> Vec3 {
+                Vec3(self.0 * k, self.1 * k, self.2 * k)
+            }
+        }
+        impl DivAssign<f32> for Vec3 {
+            fn div_assign(&mut self, k: f32) {
+                *self = Vec3(self.0 / k, self.1 / k, self.2 / k);
+            }
+        }
+        impl Shl<u32> for Vec3 {
+            type Output = u32;
+            fn shl(self, n: u32) -> u32 {
+                (self.0 as u32) << n
+            }
+        }
+        impl MixedOps for Vec3 {}
+
+        let mut v = unsafe { Imp::new(Vec3(1.0, 2.0, 4.0)) };
+        assert_eq!(v.clone() * 2.0, Vec3(2.0, 4.0, 8.0));
+        let v2 = v.clone();
+        v /= 2.0;
+        assert_eq!(*Imp::read(&v2), Vec3(0.5, 1.0, 2.0));
+        let g = GuardedImp::new(Vec3(3.0, 0.0, 0.0));
+        assert_eq!(*(g << 2).read(), 12);
+    }
+}
+
+mod primitive_lhs {
+    use crate::{GuardedImp, Imp};
+
+    #[test]
+    fn arithmetic() {
+        let p = unsafe { Imp::new(4) };
+        assert_eq!(10 - p.clone(), 6);
+        assert_eq!(2 * p.clone(), 8);
+        assert_eq!(9 / p.clone(), 2);
+        assert_eq!(9 % p.clone(), 1);
+        assert_eq!(1 + p, 5);
+
+        let f = unsafe { Imp::new(1.5) };
+        assert_eq!(2.0 * f, 3.0);
+    }
+
+    #[test]
+    fn bits() {
+        let p = unsafe { Imp::new(0b0110u8) };
+        assert_eq!(0b1100 & p.clone(), 0b0100);
+        assert_eq!(0b1000 | p.clone(), 0b1110);
+        assert_eq!(0b1111 ^ p.clone(), 0b1001);
+        assert_eq!(1 << unsafe { Imp::new(3u8) }, 8);
+        assert_eq!(16 >> unsafe { Imp::new(2u8) }, 4);
+    }
+
+    #[test]
+    fn assign() {
+        let mut x = 10;
+        x -= unsafe { Imp::new(3) };
+        x *= GuardedImp::new(2);
+        assert_eq!(x, 14);
+    }
+
+    #[test]
+    fn output_is_a_handle() {
+        let p = GuardedImp::new(4i64);
+        let r: GuardedImp<i64> = 10 - p;
+        assert_eq!(*r.read(), 6);
+    }
+}
+
+mod ref_ops {
+    use crate::{GuardedImp, Imp};
+
+    #[test]
+    fn ref_ref() {
+        let a = unsafe { Imp::new(3) };
+        let b = unsafe { Imp::new(4) };
+        assert_eq!(&a + &b, 7);
+        assert_eq!(&a * &b, 12);
+        assert_eq!(Imp::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn ref_ref_same_value() {
+        let n = GuardedImp::new(5);
+        assert_eq!(*(&n - &n.clone()).read(), 0);
+        assert_eq!(*(&n + &n).read(), 10);
+    }
+
+    #[test]
+    fn mixed_borrowed() {
+        let a = unsafe { Imp::new(6) };
+        let b = unsafe { Imp::new(2) };
+        assert_eq!(&a - b.clone(), 4);
+        assert_eq!(a.clone() / &b, 3);
+        assert_eq!(&a << 1, 12);
+        assert_eq!(&a % 4, 2);
+        assert_eq!(Imp::strong_count(&a), 1);
+        assert_eq!(Imp::strong_count(&b), 1);
+    }
+
+    #[test]
+    fn unary_borrowed() {
+        let a = unsafe { Imp::new(6) };
+        let t = GuardedImp::new(true);
+        assert_eq!(-&a, -6);
+        assert_eq!(!&a, !6);
+        assert!(!*(!&t).read());
+        assert_eq!(a, 6);
+    }
+
+    #[test]
+    fn assign_borrowed() {
+        let mut a = unsafe { Imp::new(6) };
+        let b = unsafe { Imp::new(2) };
+        a += &b;
+        a *= &b;
+        assert_eq!(a, 16);
+        assert_eq!(Imp::strong_count(&b), 1);
+
+        let c = a.clone();
+        a -= &c;
+        assert_eq!(c, 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ref_ref_same_mutex() {
+        let n = crate::MutexImp::new(5);
+        assert_eq!(*(&n + &n.clone()).lock(), 10);
+    }
+}
+
+mod iter {
+    use std::collections::HashSet;
+
+    use crate::{GuardedImp, Imp};
+
+    #[test]
+    fn sum_product() {
+        let v: Vec<Imp<f64>> = [1.0, 2.0, 3.0]
+            .into_iter()
+            .map(|f| unsafe { Imp::new(f) })
+            .collect();
+        let total: f64 = v.iter().sum();
+        assert_eq!(total, 6.0);
+        let product: f64 = v.into_iter().product();
+        assert_eq!(product, 6.0);
+    }
+
+    #[test]
+    fn sum_guarded() {
+        let v = vec![GuardedImp::new(2), GuardedImp::new(5)];
+        let total: GuardedImp<i32> = v.iter().sum();
+        assert_eq!(*total.read(), 7);
+        let product: GuardedImp<i32> = v.into_iter().product();
+        assert_eq!(*product.read(), 10);
+    }
+
+    #[test]
+    fn extend() {
+        let mut p = unsafe { Imp::new(vec![1]) };
+        let p2 = p.clone();
+        p.extend([2, 3]);
+        assert_eq!(*p2, vec![1, 2, 3]);
+
+        let mut s = GuardedImp::new(String::from("yo"));
+        s.extend(['!', '?']);
+        assert_eq!(*s.read(), "yo!?");
+    }
+
+    #[test]
+    fn from_iter() {
+        let p: GuardedImp<Vec<i32>> = (1..4).collect();
+        assert_eq!(*p.read(), vec![1, 2, 3]);
+        let p: GuardedImp<HashSet<i32>> = [1, 1, 2].into_iter().collect();
+        assert_eq!(p.read().len(), 2);
+        let s: GuardedImp<String> = ["a", "b"].into_iter().collect();
+        assert_eq!(*s.read(), "ab");
+    }
+}
+
+// ByPtr keys hash by address, which mutating the value does not change.
+#[allow(clippy::mutable_key_type)]
+mod hash {
+    use std::collections::{BTreeSet, HashMap, HashSet};
+
+    use crate::{ByPtr, GuardedImp, Imp};
+
+    #[test]
+    fn hash_by_value() {
+        let p1 = unsafe { Imp::new(String::from("yo")) };
+        let p2 = unsafe { Imp::new(String::from("yo")) };
+        let mut set = HashSet::new();
+        set.insert(p1);
+        assert!(set.contains(&p2));
+    }
+
+    #[test]
+    fn hash_by_ptr() {
+        let p1 = unsafe { Imp::new(5) };
+        let p2 = unsafe { Imp::new(5) };
+        let mut set = HashSet::new();
+        set.insert(ByPtr(p1.clone()));
+        assert!(set.contains(&ByPtr(p1.clone())));
+        assert!(!set.contains(&ByPtr(p2)));
+    }
+
+    #[test]
+    fn by_ptr_survives_mutation() {
+        let mut p = unsafe { Imp::new(5) };
+        let mut map = HashMap::new();
+        map.insert(ByPtr(p.clone()), "node");
+        *p += 1;
+        assert_eq!(map[&ByPtr(p)], "node");
+    }
+
+    #[test]
+    fn by_ptr_ord() {
+        let p1 = GuardedImp::new(1);
+        let p2 = GuardedImp::new(1);
+        let set: BTreeSet<_> = [ByPtr(p1.clone()), ByPtr(p2), ByPtr(p1)]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn by_ptr_weak() {
+        let p = unsafe { Imp::new(5) };
+        let w1 = ByPtr(Imp::downgrade(&p));
+        let w2 = ByPtr(Imp::downgrade(&p));
+        assert_eq!(w1, w2);
+        assert_ne!(w1, ByPtr(crate::WeakImp::new()));
+    }
+
+    #[test]
+    fn by_ptr_mapped() {
+        let mut v = unsafe { Imp::new(vec![1]) };
+        let first = Imp::map(&v, |v| &v[0], |v| &mut v[0]);
+        let mut set = HashSet::new();
+        set.insert(ByPtr(first.clone()));
+        v.extend(0..100);
+        assert!(set.contains(&ByPtr(first)));
+        assert!(!set.contains(&ByPtr(Imp::map(&v, |v| &v[0], |v| &mut v[0]))));
+    }
+
+    #[test]
+    fn by_ptr_observable() {
+        let mut p = unsafe { crate::ObservableImp::new(5) };
+        let mut set = HashSet::new();
+        set.insert(ByPtr(p.clone()));
+        p += 1;
+        assert!(set.contains(&ByPtr(p)));
+        assert!(!set.contains(&ByPtr(unsafe { crate::ObservableImp::new(6) })));
+    }
+
+    #[test]
+    fn by_ptr_history() {
+        let mut p = unsafe { crate::HistoryImp::new(5) };
+        let mut set = HashSet::new();
+        set.insert(ByPtr(p.clone()));
+        p += 1;
+        assert!(set.contains(&ByPtr(p)));
+        assert!(!set.contains(&ByPtr(unsafe { crate::HistoryImp::new(6) })));
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "std"))]
+mod serialization {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{deserialize_unchecked, identity, GuardedImp, Imp, SyncImp, WeakImp};
+
+    #[derive(Serialize, Deserialize)]
+    struct Node {
+        name: String,
+        parent: WeakImp<Node>,
+        children: Vec<Imp<Node>>,
+    }
+
+    #[test]
+    fn transparent() {
+        let p = unsafe { Imp::new(vec![1, 2, 3]) };
+        let json = serde_json::to_string(&p).unwrap();
+        assert_eq!(json, "[1,2,3]");
+        let p: Imp<Vec<i32>> =
+            unsafe { deserialize_unchecked(|| serde_json::from_str(&json)) }.unwrap();
+        assert_eq!(p, vec![1, 2, 3]);
+        assert!(serde_json::from_str::<Imp<Vec<i32>>>(&json).is_err());
+
+        let g: GuardedImp<String> = serde_json::from_str(r#""yo""#).unwrap();
+        assert_eq!(serde_json::to_string(&g).unwrap(), r#""yo""#);
+        let s: SyncImp<i32> = serde_json::from_str("5").unwrap();
+        assert_eq!(serde_json::to_string(&s).unwrap(), "5");
+    }
+
+    #[test]
+    fn clones_stay_shared() {
+        let p = unsafe { Imp::new(String::from("shared")) };
+        let q = unsafe { Imp::new(String::from("other")) };
+        let v = vec![p.clone(), q, p];
+        let json = identity::scope(|| serde_json::to_string(&v).unwrap());
+
+        let mut v: Vec<Imp<String>> =
+            identity::scope(|| unsafe { deserialize_unchecked(|| serde_json::from_str(&json)) })
+                .unwrap();
+        assert!(Imp::ptr_eq(&v[0], &v[2]));
+        assert!(!Imp::ptr_eq(&v[0], &v[1]));
+        v[0].push('!');
+        assert_eq!(*Imp::read(&v[2]), "shared!");
+        assert_eq!(Imp::strong_count(&v[0]), 2);
+    }
+
+    #[test]
+    fn weak_cycles_round_trip() {
+        let root = unsafe {
+            Imp::new(Node {
+                name: "root".into(),
+                parent: WeakImp::new(),
+                children: vec![],
+            })
+        };
+        let mut r = root.clone();
+        for name in ["a", "b"] {
+            r.children.push(unsafe {
+                Imp::new(Node {
+                    name: name.into(),
+                    parent: Imp::downgrade(&root),
+                    children: vec![],
+                })
+            });
+        }
+        let json = identity::scope(|| serde_json::to_string(&root).unwrap());
+
+        let root: Imp<Node> =
+            identity::scope(|| unsafe { deserialize_unchecked(|| serde_json::from_str(&json)) })
+                .unwrap();
+        assert_eq!(root.children.len(), 2);
+        for child in &root.children {
+            assert!(Imp::ptr_eq(&child.parent.upgrade().unwrap(), &root));
+        }
+        assert_eq!(root.children[1].name, "b");
+        assert_eq!(Imp::strong_count(&root), 1);
+    }
+
+    #[test]
+    fn strong_cycle_is_an_error() {
+        #[derive(Serialize, Deserialize)]
+        struct Cyclic {
+            next: Option<Imp<Cyclic>>,
+        }
+
+        let mut p = unsafe { Imp::new(Cyclic { next: None }) };
+        p.next = Some(p.clone());
+        let json = identity::scope(|| serde_json::to_string(&p).unwrap());
+        p.next = None;
+
+        let err = identity::scope(|| unsafe {
+            deserialize_unchecked(|| serde_json::from_str::<Imp<Cyclic>>(&json))
+                .err()
+                .unwrap()
+        });
+        assert!(err.to_string().contains("strong reference cycle"));
+    }
+
+    #[test]
+    fn weak_requires_scope() {
+        let p = unsafe { Imp::new(5) };
+        assert!(serde_json::to_string(&Imp::downgrade(&p)).is_err());
+        assert!(serde_json::from_str::<WeakImp<i32>>("null").is_err());
+    }
+
+    #[test]
+    fn weak_only_value_dies_with_scope() {
+        let p = unsafe { Imp::new(5) };
+        let json = identity::scope(|| serde_json::to_string(&Imp::downgrade(&p)).unwrap());
+        let w: WeakImp<i32> = identity::scope(|| {
+            let w: WeakImp<i32> =
+                unsafe { deserialize_unchecked(|| serde_json::from_str(&json)) }.unwrap();
+            assert_eq!(w.upgrade().unwrap(), 5);
+            w
+        });
+        assert!(w.upgrade().is_none());
+    }
+
+    #[test]
+    fn freed_allocation_is_not_reused() {
+        let json = identity::scope(|| {
+            let a = serde_json::to_string(&unsafe { Imp::new(1) }).unwrap();
+            let b = serde_json::to_string(&unsafe { Imp::new(2) }).unwrap();
+            (a, b)
+        });
+        assert_eq!(json.0, r#"{"id":0,"value":1}"#);
+        assert_eq!(json.1, r#"{"id":1,"value":2}"#);
+    }
+
+    #[test]
+    fn weak_requires_unchecked() {
+        let p = unsafe { Imp::new(5) };
+        let json = identity::scope(|| serde_json::to_string(&Imp::downgrade(&p)).unwrap());
+        let err = identity::scope(|| serde_json::from_str::<WeakImp<i32>>(&json).err().unwrap());
+        assert!(err.to_string().contains("deserialize_unchecked"));
+    }
+}
@@ -0,0 +1,154 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{Imp, ImpRefMut};
+
+/// Groups changes to several [`Imp`] pointers, of any types, so they are undone together.
+///
+/// The first time a value is written through the transaction a copy of it is kept.
+/// Dropping the transaction without calling [`Transaction::commit`], like when returning early with `?`
+/// or unwinding from a panic, puts all the copies back, in reverse order.
+/// Only changes made after the copy was taken are undone,
+/// so values should be written through [`Transaction::write`] or recorded with [`Transaction::record`] first.
+///
+/// # Panics
+/// Dropping an uncommitted transaction panics if one of its values is still borrowed,
+/// after putting back all the others. With the `std` feature it does not panic while already unwinding,
+/// and the borrowed values are left as they are.
+///
+/// # Examples
+/// ```
+/// use interior_mutability_pointer::{Imp, Transaction};
+/// let balance = unsafe { Imp::new(100) };
+/// let log = unsafe { Imp::new(Vec::<String>::new()) };
+/// let r: Result<(), &str> = Transaction::run(|tx| {
+///     *tx.write(&balance) -= 150;
+///     tx.write(&log).push("withdrew 150".into());
+///     if *balance < 0 {
+///         return Err("insufficient funds");
+///     }
+///     Ok(())
+/// });
+/// assert!(r.is_err());
+/// assert_eq!(balance, 100);
+/// assert!(log.is_empty());
+/// ```
+#[must_use = "the changes are undone when the transaction is dropped without being committed"]
+pub struct Transaction<'a> {
+    undo: Undo<'a>,
+}
+
+// The address of each recorded value, with the closure putting its copy back, which returns false if it is borrowed.
+type Undo<'a> = Vec<(*const (), Box<dyn FnOnce() -> bool + 'a>)>;
+
+impl<'a> Transaction<'a> {
+    /// Returns a transaction that has not recorded any values yet.
+    pub fn new() -> Self {
+        Self { undo: Vec::new() }
+    }
+
+    /// Runs `f` in a new transaction, which is committed if `f` returns `Ok`, and otherwise undone.
+    ///
+    /// # Arguments
+    /// * `f` - Makes the changes, through the transaction it is passed
+    pub fn run<R, E>(f: impl FnOnce(&mut Transaction<'a>) -> Result<R, E>) -> Result<R, E> {
+        let mut tx = Transaction::new();
+        let r = f(&mut tx)?;
+        tx.commit();
+        Ok(r)
+    }
+
+    /// Keeps a copy of the value to put back, unless it was already recorded by this transaction.
+    ///
+    /// Allows changing the value through the pointer itself afterwards, with `DerefMut` or the operators.
+    ///
+    /// # Panics
+    /// Panics if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn record<T: Clone + 'a>(&mut self, p: &Imp<T>) {
+        let addr = p.v.as_ptr() as *const ();
+        if self.undo.iter().any(|(a, _)| *a == addr) {
+            return;
+        }
+        let original = Imp::read(p).clone();
+        let p = p.clone();
+        self.undo.push((
+            addr,
+            Box::new(move || match Imp::try_write(&p) {
+                Ok(mut v) => {
+                    *v = original;
+                    true
+                }
+                Err(_) => false,
+            }),
+        ));
+    }
+
+    /// Mutably borrows the value, recording it first if this is the first time it is written through the transaction.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn write<'b, T: Clone + 'a>(&'b mut self, p: &'b Imp<T>) -> ImpRefMut<'b, T> {
+        self.record(p);
+        Imp::write(p)
+    }
+
+    /// Keeps the changes, forgetting the recorded copies.
+    pub fn commit(mut self) {
+        self.undo.clear();
+    }
+
+    /// Puts the recorded copies back right away, the same as dropping the transaction.
+    ///
+    /// # Panics
+    /// Panics if one of the values is still borrowed, after putting back all the others.
+    #[track_caller]
+    pub fn rollback(mut self) {
+        let failed = self.restore();
+        report(failed);
+    }
+
+    /*
+        Puts back every recorded copy whose value is not borrowed, returning how many were.
+    */
+    fn restore(&mut self) -> usize {
+        let mut failed = 0;
+        while let Some((_, restore)) = self.undo.pop() {
+            if !restore() {
+                failed += 1;
+            }
+        }
+        failed
+    }
+}
+
+/*
+    Panics if some of the values could not be put back.
+*/
+#[track_caller]
+fn report(failed: usize) {
+    if failed > 0 {
+        panic!(
+            "could not undo the changes to {} of the values, as they are still borrowed",
+            failed
+        );
+    }
+}
+
+impl Default for Transaction<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        let failed = self.restore();
+        // Panicking again while unwinding would abort.
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+        report(failed);
+    }
+}
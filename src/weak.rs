@@ -0,0 +1,125 @@
+use alloc::rc::Weak;
+use core::cell::RefCell;
+
+use crate::Imp;
+
+/// A non-owning handle to the value of an [`Imp`].
+///
+/// Does not keep the value alive, which allows cycles like parent pointers in trees
+/// without leaking. Access the value by upgrading it into an [`Imp`].
+pub struct WeakImp<T: ?Sized> {
+    pub(crate) v: Weak<RefCell<T>>,
+}
+
+impl<T> WeakImp<T> {
+    /// Returns a weak pointer not pointing to anything.
+    /// Calling [`WeakImp::upgrade`] on it always returns `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::WeakImp;
+    /// let w: WeakImp<i32> = WeakImp::new();
+    /// assert!(w.upgrade().is_none());
+    /// ```
+    pub fn new() -> Self {
+        Self { v: Weak::new() }
+    }
+}
+
+impl<T: ?Sized> WeakImp<T> {
+    /// Attempts to upgrade the weak pointer to an [`Imp`].
+    /// Returns `None` if the value has already been dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use interior_mutability_pointer::Imp;
+    /// let p = unsafe { Imp::new(5) };
+    /// let w = Imp::downgrade(&p);
+    /// assert_eq!(w.upgrade().unwrap(), 5);
+    /// drop(p);
+    /// assert!(w.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Imp<T>> {
+        let v = self.v.upgrade()?;
+        #[cfg(feature = "checked")]
+        crate::checked::release(v.as_ptr());
+        Some(Imp { v })
+    }
+
+    /// Returns the number of [`Imp`] pointers pointing to the value.
+    pub fn strong_count(&self) -> usize {
+        self.v.strong_count()
+    }
+
+    /// Returns the number of `WeakImp` pointers pointing to the value,
+    /// or 0 if no [`Imp`] pointers remain.
+    pub fn weak_count(&self) -> usize {
+        self.v.weak_count()
+    }
+
+    /// Returns true if two weak pointers point to the same allocation,
+    /// or if both don't point to anything.
+    ///
+    /// # Arguments
+    /// * `other` - The other pointer to compare to
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.v.ptr_eq(&other.v)
+    }
+}
+
+/*
+    Implements cloning the pointer.
+*/
+mod clone_impl {
+    use super::WeakImp;
+
+    impl<T: ?Sized> Clone for WeakImp<T> {
+        fn clone(&self) -> Self {
+            Self { v: self.v.clone() }
+        }
+    }
+}
+
+/*
+    Allows creating an empty weak pointer with Default.
+*/
+mod default_impl {
+    use super::WeakImp;
+
+    impl<T> Default for WeakImp<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+/*
+    Debug does not print the value, as that could recurse through cycles.
+*/
+mod debug_impl {
+    use core::fmt::Debug;
+
+    use super::WeakImp;
+
+    impl<T: ?Sized> Debug for WeakImp<T> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "(WeakImp)")
+        }
+    }
+}
+
+/*
+    Allows coercing into weak pointers to trait objects, the same way as Imp.
+*/
+#[cfg(feature = "nightly")]
+mod unsize_impl {
+    use core::{
+        marker::Unsize,
+        ops::{CoerceUnsized, DispatchFromDyn},
+    };
+
+    use super::WeakImp;
+
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<WeakImp<U>> for WeakImp<T> {}
+    impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<WeakImp<U>> for WeakImp<T> {}
+}